@@ -1,6 +1,8 @@
 use hashbrown::{HashMap, HashSet};
 
-use consistency::util::{ConstrainedLinearization, DiGraph};
+use consistency::util::{Budget, ConstrainedLinearization, DiGraph, OutOfBudget};
+use consistency::witness::{EdgeKind, WitnessEdge};
+use consistency::Consistency;
 
 use slog::Logger;
 
@@ -97,6 +99,384 @@ impl AtomicHistoryPO {
 
         ww
     }
+
+    // adds wr to vis, then infers ww from vis and grows vis with it until
+    // nothing changes; what ww adds to vis depends on the level. the budget
+    // is checked on every round
+    pub fn saturate(
+        &mut self,
+        level: Consistency,
+        budget: &Budget,
+    ) -> Result<HashMap<Variable, DiGraph<TransactionId>>, OutOfBudget> {
+        let wr = self.get_wr();
+        self.vis_includes(&wr);
+        self.vis_is_trans();
+
+        loop {
+            budget.check(0)?;
+
+            let ww = self.causal_ww();
+            let n_edge = self.n_vis_edge();
+
+            match level {
+                // ww is only an order on the writes, vis stays (so U wr)+
+                Consistency::Causal => return Ok(ww),
+                // co;vis is contained in vis
+                Consistency::Prefix => {
+                    let mut co_vis: DiGraph<TransactionId> = Default::default();
+                    for (_, ww_x) in ww.iter() {
+                        for (t1, t2s) in ww_x.adj_map.iter() {
+                            for t2 in t2s.iter() {
+                                if let Some(t3s) = self.vis.adj_map.get(t2) {
+                                    for &t3 in t3s.iter() {
+                                        co_vis.add_edge(*t1, t3);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    self.vis_includes(&co_vis);
+                }
+                // conflicting writes are visible to each other
+                Consistency::SnapshotIsolation | Consistency::Serializable => {
+                    for (_, ww_x) in ww.iter() {
+                        self.vis_includes(ww_x);
+                    }
+                }
+                _ => unreachable!("no saturation for {:?}", level),
+            }
+
+            self.vis_is_trans();
+
+            if self.n_vis_edge() == n_edge {
+                return Ok(ww);
+            }
+        }
+    }
+
+    fn n_vis_edge(&self) -> usize {
+        self.vis.adj_map.values().map(|vs| vs.len()).sum()
+    }
+
+    // adds a transaction after the ones of its session added so far; it may
+    // read from transactions added later. vis stays closed, but is not
+    // saturated again
+    pub fn add_transaction(&mut self, txn_id: TransactionId, txn_info: TransactionInfo) {
+        let root = self.root;
+        let prev = self
+            .txns_info
+            .keys()
+            .filter(|t| t.0 == txn_id.0 && t.1 < txn_id.1)
+            .max()
+            .cloned()
+            .unwrap_or(root);
+
+        self.so.add_edge(prev, txn_id);
+        self.vis.add_vertex(txn_id);
+        self.vis_add_edge(prev, txn_id);
+
+        for &var in txn_info.1.iter() {
+            let wr_x = self.wr_rel.entry(var).or_insert_with(Default::default);
+            wr_x.add_vertex(txn_id);
+            // readers added before their writer
+            let readers: Vec<_> = wr_x.adj_map[&txn_id].iter().cloned().collect();
+            for reader in readers {
+                self.vis_add_edge(txn_id, reader);
+            }
+        }
+        for (&var, &writer) in txn_info.0.iter() {
+            self.wr_rel
+                .entry(var)
+                .or_insert_with(Default::default)
+                .add_edge(writer, txn_id);
+            if writer == root {
+                self.txns_info
+                    .entry(root)
+                    .or_insert_with(Default::default)
+                    .1
+                    .insert(var);
+            }
+            self.vis_add_edge(writer, txn_id);
+        }
+
+        self.txns_info.insert(txn_id, txn_info);
+    }
+
+    // adds an edge to the closed vis with the ones it implies; false if it
+    // was there already
+    pub fn vis_add_edge(&mut self, u: TransactionId, v: TransactionId) -> bool {
+        if self.vis.has_edge(&u, &v) {
+            return false;
+        }
+
+        let mut after: Vec<_> = self
+            .vis
+            .adj_map
+            .get(&v)
+            .map_or(Vec::new(), |ts| ts.iter().cloned().collect());
+        after.push(v);
+
+        let mut before: Vec<_> = self
+            .vis
+            .adj_map
+            .iter()
+            .filter(|(_, ts)| ts.contains(&u))
+            .map(|(&t, _)| t)
+            .collect();
+        before.push(u);
+
+        for t in before {
+            self.vis.add_edges(t, &after);
+        }
+
+        true
+    }
+
+    // `saturate` again after adding transactions, growing vis edge by edge
+    // instead of closing it from scratch
+    pub fn resaturate(&mut self, level: Consistency) -> HashMap<Variable, DiGraph<TransactionId>> {
+        loop {
+            let ww = self.causal_ww();
+
+            let mut implied = Vec::new();
+            match level {
+                Consistency::Causal => return ww,
+                Consistency::Prefix => {
+                    for (_, ww_x) in ww.iter() {
+                        for (&t1, t2s) in ww_x.adj_map.iter() {
+                            for t2 in t2s.iter() {
+                                if let Some(t3s) = self.vis.adj_map.get(t2) {
+                                    implied.extend(t3s.iter().map(|&t3| (t1, t3)));
+                                }
+                            }
+                        }
+                    }
+                }
+                Consistency::SnapshotIsolation | Consistency::Serializable => {
+                    for (_, ww_x) in ww.iter() {
+                        for (&t1, t2s) in ww_x.adj_map.iter() {
+                            implied.extend(t2s.iter().map(|&t2| (t1, t2)));
+                        }
+                    }
+                }
+                _ => unreachable!("no saturation for {:?}", level),
+            }
+
+            let mut grown = false;
+            for (u, v) in implied {
+                grown |= self.vis_add_edge(u, v);
+            }
+
+            if !grown {
+                return ww;
+            }
+        }
+    }
+
+    // removes `txns` and the reads from them; vis keeps what they implied
+    // for the others
+    pub fn remove_transactions(&mut self, txns: &HashSet<TransactionId>) {
+        self.txns_info.retain(|t, _| !txns.contains(t));
+        for (_, (reads, _)) in self.txns_info.iter_mut() {
+            reads.retain(|_, writer| !txns.contains(writer));
+        }
+
+        for graph in vec![&mut self.so, &mut self.vis]
+            .into_iter()
+            .chain(self.wr_rel.values_mut())
+        {
+            graph.adj_map.retain(|t, _| !txns.contains(t));
+            for (_, ts) in graph.adj_map.iter_mut() {
+                ts.retain(|t| !txns.contains(t));
+            }
+        }
+    }
+
+    pub fn causal_rw(
+        &self,
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> HashMap<Variable, DiGraph<TransactionId>> {
+        let mut rw: HashMap<Variable, DiGraph<TransactionId>> = Default::default();
+
+        for (&x, ww_x) in ww.iter() {
+            let mut rw_x: DiGraph<TransactionId> = Default::default();
+            if let Some(wr_x) = self.wr_rel.get(&x) {
+                for (t1, t2s) in ww_x.adj_map.iter() {
+                    // readers of t1 must come before t1's overwriters
+                    if let Some(t3s) = wr_x.adj_map.get(t1) {
+                        for t3 in t3s.iter() {
+                            for t2 in t2s.iter() {
+                                if t3 != t2 {
+                                    rw_x.add_edge(*t3, *t2);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            rw.insert(x, rw_x);
+        }
+
+        rw
+    }
+
+    pub fn cycle_witness(
+        &self,
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+        rw: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> Option<Vec<WitnessEdge>> {
+        let mut dep = self.dependency_graph(ww);
+        for (_, rw_x) in rw.iter() {
+            dep.union_with(rw_x);
+        }
+
+        dep.find_cycle()
+            .map(|cycle| self.label_cycle(&cycle, ww, rw))
+    }
+
+    // one shortest cycle through every dependency and anti-dependency edge,
+    // preferring cycles with fewer anti-dependencies
+    pub fn dependency_cycles(
+        &self,
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+        rw: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> Vec<Vec<WitnessEdge>> {
+        let dep = self.dependency_graph(ww);
+        let mut full = dep.clone();
+        for (_, rw_x) in rw.iter() {
+            full.union_with(rw_x);
+        }
+
+        let mut seen: HashSet<Vec<TransactionId>> = Default::default();
+        let mut cycles = Vec::new();
+
+        // cycles stay inside a strongly connected component, so the paths
+        // are searched among the edges of one component at a time
+        for component in full.strongly_connected_components() {
+            let inside: HashSet<TransactionId> = component.iter().cloned().collect();
+            let restrict = |graph: &DiGraph<TransactionId>| {
+                let mut restricted: DiGraph<TransactionId> = Default::default();
+                for u in component.iter() {
+                    if let Some(vs) = graph.adj_map.get(u) {
+                        for v in vs.iter().filter(|v| inside.contains(v)) {
+                            restricted.add_edge(*u, *v);
+                        }
+                    }
+                }
+                restricted
+            };
+            let full_c = restrict(&full);
+            if full_c.adj_map.values().all(|vs| vs.is_empty()) {
+                continue;
+            }
+            let dep_c = restrict(&dep);
+
+            for (u, vs) in full_c.adj_map.iter() {
+                for v in vs.iter() {
+                    let path = if dep_c.has_edge(u, v) {
+                        dep_c.find_path(v, u)
+                    } else {
+                        dep_c.find_path(v, u).or_else(|| full_c.find_path(v, u))
+                    };
+                    if let Some(mut cycle) = path {
+                        // rotate, so that the same cycle is found once
+                        let pos = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+                        cycle.rotate_left(pos);
+                        if seen.insert(cycle.clone()) {
+                            cycles.push(self.label_cycle(&cycle, ww, rw));
+                        }
+                    }
+                }
+            }
+        }
+
+        cycles
+    }
+
+    // every so, wr, ww and rw edge among `vertices`, with so reduced to
+    // the closest predecessor in the same session
+    pub fn subgraph(
+        &self,
+        vertices: &HashSet<TransactionId>,
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+        rw: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> Vec<WitnessEdge> {
+        let mut edges = Vec::new();
+
+        for &v in vertices.iter() {
+            if let Some(&u) = vertices
+                .iter()
+                .filter(|u| self.so.has_edge(u, &v))
+                .max_by_key(|u| (u.0 == v.0, u.1))
+            {
+                edges.push((u, EdgeKind::SO, v));
+            }
+        }
+
+        for (rel, kind) in [
+            (&self.wr_rel, EdgeKind::WR as fn(Variable) -> EdgeKind),
+            (ww, EdgeKind::WW),
+            (rw, EdgeKind::RW),
+        ]
+        .iter()
+        {
+            for (&x, rel_x) in rel.iter() {
+                for (u, vs) in rel_x.adj_map.iter() {
+                    if vertices.contains(u) {
+                        for v in vs.iter().filter(|v| vertices.contains(v)) {
+                            edges.push((*u, kind(x), *v));
+                        }
+                    }
+                }
+            }
+        }
+
+        edges.sort_unstable_by_key(|&(u, _, v)| (u, v));
+
+        edges
+    }
+
+    fn dependency_graph(
+        &self,
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> DiGraph<TransactionId> {
+        let mut dep = self.so.clone();
+        dep.union_with(&self.get_wr());
+        for (_, ww_x) in ww.iter() {
+            dep.union_with(ww_x);
+        }
+        dep
+    }
+
+    fn label_cycle(
+        &self,
+        cycle: &[TransactionId],
+        ww: &HashMap<Variable, DiGraph<TransactionId>>,
+        rw: &HashMap<Variable, DiGraph<TransactionId>>,
+    ) -> Vec<WitnessEdge> {
+        (0..cycle.len())
+            .map(|i| {
+                let (u, v) = (cycle[i], cycle[(i + 1) % cycle.len()]);
+                let find = |rel: &HashMap<Variable, DiGraph<TransactionId>>| {
+                    rel.iter()
+                        .find(|(_, rel_x)| rel_x.has_edge(&u, &v))
+                        .map(|(&x, _)| x)
+                };
+                let kind = if self.so.has_edge(&u, &v) {
+                    EdgeKind::SO
+                } else if let Some(x) = find(&self.wr_rel) {
+                    EdgeKind::WR(x)
+                } else if let Some(x) = find(ww) {
+                    EdgeKind::WW(x)
+                } else if let Some(x) = find(rw) {
+                    EdgeKind::RW(x)
+                } else {
+                    unreachable!("cycle edge must come from a relation")
+                };
+                (u, kind, v)
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
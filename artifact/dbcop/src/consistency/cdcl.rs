@@ -0,0 +1,485 @@
+use std::time::Duration;
+
+use consistency::solver::{within, Clause, IncrementalSat, SatBackend, SatError};
+use consistency::util::Budget;
+
+// literal of variable `v` (from 0) is `2 * v`, its negation `2 * v + 1`
+type Lit = usize;
+
+fn var(lit: Lit) -> usize {
+    lit >> 1
+}
+
+fn neg(lit: Lit) -> Lit {
+    lit ^ 1
+}
+
+// in-process conflict driven clause learning solver: two watched literals,
+// first UIP learning, VSIDS with phase saving and Luby restarts
+#[derive(Debug, Clone, Default)]
+pub struct CdclSolver {
+    timeout: Option<Duration>,
+}
+
+impl CdclSolver {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        CdclSolver { timeout }
+    }
+}
+
+impl SatBackend for CdclSolver {
+    fn solve(
+        &self,
+        n_variable: usize,
+        clauses: &[Clause],
+        budget: &Budget,
+    ) -> Result<Option<Vec<bool>>, SatError> {
+        let mut solver = self.incremental(n_variable);
+        for clause in clauses.iter() {
+            solver.add_clause(clause);
+        }
+        solver.solve(budget)
+    }
+
+    fn incremental<'a>(&'a self, n_variable: usize) -> Box<dyn IncrementalSat + 'a> {
+        Box::new(Incremental {
+            search: Search::new(n_variable),
+            timeout: self.timeout,
+            unsatisfiable: false,
+        })
+    }
+}
+
+// learnt clauses, activities and saved phases carry over to the next call
+struct Incremental {
+    search: Search,
+    timeout: Option<Duration>,
+    unsatisfiable: bool,
+}
+
+impl IncrementalSat for Incremental {
+    fn add_clause(&mut self, clause: &[(bool, usize)]) {
+        self.search.backtrack(0);
+        let lits = clause
+            .iter()
+            .map(|&(sign, v)| if sign { 2 * (v - 1) } else { 2 * (v - 1) + 1 })
+            .collect();
+        if !self.search.add_clause(lits) {
+            self.unsatisfiable = true;
+        }
+    }
+
+    fn solve(&mut self, budget: &Budget) -> Result<Option<Vec<bool>>, SatError> {
+        let budget = within(budget, self.timeout);
+        budget.check(0)?;
+
+        if self.unsatisfiable {
+            return Ok(None);
+        }
+
+        self.search.backtrack(0);
+        let decision = self.search.run(&budget)?;
+        self.unsatisfiable = decision.is_none();
+
+        Ok(decision.map(|model| {
+            let mut assignments = vec![false];
+            assignments.extend(model);
+            assignments
+        }))
+    }
+}
+
+// max-heap of variables ordered by activity
+#[derive(Debug, Default)]
+struct VarOrder {
+    heap: Vec<usize>,
+    position: Vec<Option<usize>>,
+}
+
+impl VarOrder {
+    fn new(n_variable: usize) -> Self {
+        VarOrder {
+            heap: (0..n_variable).collect(),
+            position: (0..n_variable).map(Some).collect(),
+        }
+    }
+
+    fn contains(&self, v: usize) -> bool {
+        self.position[v].is_some()
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.position[self.heap[i]] = Some(i);
+        self.position[self.heap[j]] = Some(j);
+    }
+
+    fn up(&mut self, mut i: usize, activity: &[f64]) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if activity[self.heap[parent]] >= activity[self.heap[i]] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn down(&mut self, mut i: usize, activity: &[f64]) {
+        loop {
+            let mut largest = i;
+            for child in [2 * i + 1, 2 * i + 2].iter() {
+                if *child < self.heap.len()
+                    && activity[self.heap[*child]] > activity[self.heap[largest]]
+                {
+                    largest = *child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    fn insert(&mut self, v: usize, activity: &[f64]) {
+        if !self.contains(v) {
+            self.position[v] = Some(self.heap.len());
+            self.heap.push(v);
+            let i = self.heap.len() - 1;
+            self.up(i, activity);
+        }
+    }
+
+    fn bumped(&mut self, v: usize, activity: &[f64]) {
+        if let Some(i) = self.position[v] {
+            self.up(i, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let last = self.heap.len() - 1;
+        self.swap(0, last);
+        let v = self.heap.pop().unwrap();
+        self.position[v] = None;
+        if !self.heap.is_empty() {
+            self.down(0, activity);
+        }
+        Some(v)
+    }
+}
+
+#[derive(Debug)]
+struct Search {
+    clauses: Vec<Vec<Lit>>,
+    // clauses watching a literal, visited when it becomes false
+    watches: Vec<Vec<usize>>,
+    value: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    order: VarOrder,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    seen: Vec<bool>,
+}
+
+impl Search {
+    fn new(n_variable: usize) -> Self {
+        Search {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * n_variable],
+            value: vec![None; n_variable],
+            level: vec![0; n_variable],
+            reason: vec![None; n_variable],
+            phase: vec![false; n_variable],
+            activity: vec![0.0; n_variable],
+            var_inc: 1.0,
+            order: VarOrder::new(n_variable),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            seen: vec![false; n_variable],
+        }
+    }
+
+    fn lit_value(&self, lit: Lit) -> Option<bool> {
+        self.value[var(lit)].map(|b| b ^ (lit & 1 == 1))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var(lit);
+        self.value[v] = Some(lit & 1 == 0);
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+
+    fn attach(&mut self, lits: Vec<Lit>) -> usize {
+        let cref = self.clauses.len();
+        self.watches[lits[0]].push(cref);
+        self.watches[lits[1]].push(cref);
+        self.clauses.push(lits);
+        cref
+    }
+
+    // at level 0, before solving; false if the formula became unsatisfiable
+    fn add_clause(&mut self, mut lits: Vec<Lit>) -> bool {
+        lits.sort_unstable();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] == neg(w[1])) {
+            return true;
+        }
+        if lits.iter().any(|&lit| self.lit_value(lit) == Some(true)) {
+            return true;
+        }
+        lits.retain(|&lit| self.lit_value(lit) != Some(false));
+
+        match lits.len() {
+            0 => false,
+            1 => {
+                self.enqueue(lits[0], None);
+                self.propagate().is_none()
+            }
+            _ => {
+                self.attach(lits);
+                true
+            }
+        }
+    }
+
+    // returns a conflicting clause, if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = neg(self.trail[self.qhead]);
+            self.qhead += 1;
+
+            let watchers = std::mem::replace(&mut self.watches[false_lit], Vec::new());
+            let mut kept = Vec::with_capacity(watchers.len());
+            let mut conflict = None;
+
+            for (i, &cref) in watchers.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watchers[i..]);
+                    break;
+                }
+
+                if self.clauses[cref][0] == false_lit {
+                    self.clauses[cref].swap(0, 1);
+                }
+
+                let first = self.clauses[cref][0];
+                if self.lit_value(first) == Some(true) {
+                    kept.push(cref);
+                    continue;
+                }
+
+                let replacement = (2..self.clauses[cref].len())
+                    .find(|&k| self.lit_value(self.clauses[cref][k]) != Some(false));
+
+                match replacement {
+                    Some(k) => {
+                        self.clauses[cref].swap(1, k);
+                        let watched = self.clauses[cref][1];
+                        self.watches[watched].push(cref);
+                    }
+                    None => {
+                        kept.push(cref);
+                        if self.lit_value(first) == Some(false) {
+                            conflict = Some(cref);
+                        } else {
+                            self.enqueue(first, Some(cref));
+                        }
+                    }
+                }
+            }
+
+            self.watches[false_lit] = kept;
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+
+        None
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for act in self.activity.iter_mut() {
+                *act *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.order.bumped(v, &self.activity);
+    }
+
+    // first UIP; the asserting literal comes first, then one of the highest level
+    fn analyze(&mut self, mut confl: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![0];
+        let mut path_count = 0;
+        let mut p: Option<Lit> = None;
+        let mut index = self.trail.len();
+
+        loop {
+            let start = if p.is_some() { 1 } else { 0 };
+            for k in start..self.clauses[confl].len() {
+                let q = self.clauses[confl][k];
+                let v = var(q);
+                if !self.seen[v] && self.level[v] > 0 {
+                    self.seen[v] = true;
+                    self.bump(v);
+                    if self.level[v] >= self.decision_level() {
+                        path_count += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+
+            loop {
+                index -= 1;
+                if self.seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            p = Some(lit);
+            self.seen[var(lit)] = false;
+            path_count -= 1;
+            if path_count == 0 {
+                break;
+            }
+            confl = self.reason[var(lit)].expect("implied literal must have a reason");
+        }
+
+        learnt[0] = neg(p.unwrap());
+
+        for &lit in learnt.iter() {
+            self.seen[var(lit)] = false;
+        }
+
+        let mut backtrack_level = 0;
+        if learnt.len() > 1 {
+            let max_i = (1..learnt.len())
+                .max_by_key(|&i| self.level[var(learnt[i])])
+                .unwrap();
+            learnt.swap(1, max_i);
+            backtrack_level = self.level[var(learnt[1])];
+        }
+
+        self.var_inc /= 0.95;
+
+        (learnt, backtrack_level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() > level {
+            for i in (self.trail_lim[level]..self.trail.len()).rev() {
+                let lit = self.trail[i];
+                let v = var(lit);
+                self.value[v] = None;
+                self.reason[v] = None;
+                self.phase[v] = lit & 1 == 0;
+                self.order.insert(v, &self.activity);
+            }
+            self.trail.truncate(self.trail_lim[level]);
+            self.trail_lim.truncate(level);
+            self.qhead = self.trail.len();
+        }
+    }
+
+    fn luby(mut i: u64) -> u64 {
+        // 1, 1, 2, 1, 1, 2, 4, ...
+        let mut size = 1;
+        let mut seq = 0;
+        while size < i + 1 {
+            seq += 1;
+            size = 2 * size + 1;
+        }
+        while size - 1 != i {
+            size = (size - 1) / 2;
+            seq -= 1;
+            i %= size;
+        }
+        1 << seq
+    }
+
+    fn run(&mut self, budget: &Budget) -> Result<Option<Vec<bool>>, SatError> {
+        if self.propagate().is_some() {
+            return Ok(None);
+        }
+
+        let mut conflicts: u64 = 0;
+        let mut restarts = 0;
+        let mut restart_at = 100 * Self::luby(restarts);
+        // conflicts and decisions
+        let mut steps: u64 = 0;
+
+        loop {
+            if steps % 64 == 0 {
+                budget.check(0)?;
+            }
+            steps += 1;
+
+            match self.propagate() {
+                Some(confl) => {
+                    conflicts += 1;
+                    if self.decision_level() == 0 {
+                        return Ok(None);
+                    }
+
+                    let (learnt, backtrack_level) = self.analyze(confl);
+                    self.backtrack(backtrack_level);
+                    if learnt.len() == 1 {
+                        self.enqueue(learnt[0], None);
+                    } else {
+                        let asserting = learnt[0];
+                        let cref = self.attach(learnt);
+                        self.enqueue(asserting, Some(cref));
+                    }
+                }
+                None => {
+                    if conflicts >= restart_at {
+                        restarts += 1;
+                        restart_at = conflicts + 100 * Self::luby(restarts);
+                        self.backtrack(0);
+                    }
+
+                    let next = loop {
+                        match self.order.pop(&self.activity) {
+                            Some(v) if self.value[v].is_none() => break Some(v),
+                            Some(_) => continue,
+                            None => break None,
+                        }
+                    };
+
+                    match next {
+                        Some(v) => {
+                            self.trail_lim.push(self.trail.len());
+                            let lit = if self.phase[v] { 2 * v } else { 2 * v + 1 };
+                            self.enqueue(lit, None);
+                        }
+                        None => {
+                            return Ok(Some(
+                                self.value.iter().map(|b| b.unwrap_or(false)).collect(),
+                            ))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use hashbrown::{HashMap, HashSet};
+
+use consistency::witness::CommitOrder;
+use db::history::Session;
+
+type TransactionId = (usize, usize);
+type EventId = (usize, usize, usize);
+type Variable = usize;
+
+// checks a commit order against the raw sessions only, independently of the
+// searches that produce it: every committed transaction starts after its
+// session predecessor commits, and every successful read returns the own
+// latest write, or else the latest write committed before the start
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CertificateError {
+    UnknownTransaction(TransactionId),
+    Repeated(TransactionId),
+    CommitBeforeStart(TransactionId),
+    Missing(TransactionId),
+    SessionOrder {
+        before: TransactionId,
+        after: TransactionId,
+    },
+    WrongRead {
+        read: EventId,
+        value: usize,
+        expected: usize,
+    },
+    ConcurrentWrites {
+        variable: Variable,
+        first: TransactionId,
+        second: TransactionId,
+    },
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CertificateError::UnknownTransaction(t) => {
+                write!(f, "{:?} is not a committed transaction", t)
+            }
+            CertificateError::Repeated(t) => write!(f, "{:?} appears twice", t),
+            CertificateError::CommitBeforeStart(t) => write!(f, "{:?} commits before it starts", t),
+            CertificateError::Missing(t) => write!(f, "{:?} is missing", t),
+            CertificateError::SessionOrder { before, after } => {
+                write!(f, "{:?} starts before {:?} commits", after, before)
+            }
+            CertificateError::WrongRead {
+                read,
+                value,
+                expected,
+            } => write!(
+                f,
+                "read {:?} returns {}, expected {}",
+                read, value, expected
+            ),
+            CertificateError::ConcurrentWrites {
+                variable,
+                first,
+                second,
+            } => write!(
+                f,
+                "{:?} and {:?} write {} concurrently",
+                first, second, variable
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {}
+
+// `snapshot_isolation` also rejects overlapping writers of a variable
+pub fn check(
+    histories: &[Session],
+    order: &CommitOrder,
+    snapshot_isolation: bool,
+) -> Result<(), CertificateError> {
+    let points: Vec<(TransactionId, bool)> = match order {
+        CommitOrder::Serial(ref transactions) => transactions
+            .iter()
+            .map(|&t| vec![(t, false), (t, true)])
+            .flatten()
+            .collect(),
+        CommitOrder::Snapshot(ref points) => points.clone(),
+    };
+
+    // committed value of every variable, with the position of its commit
+    let mut store: HashMap<Variable, (usize, TransactionId, usize)> = HashMap::new();
+    // writes of the started transactions, with the position of their start
+    let mut pending: HashMap<TransactionId, (HashMap<Variable, usize>, usize)> = HashMap::new();
+    let mut committed: HashSet<TransactionId> = HashSet::new();
+
+    for (i, &(t, commit)) in points.iter().enumerate() {
+        // the initial values are there from the start
+        if t == (0, 0) {
+            continue;
+        }
+
+        let transaction = match histories.get(t.0.wrapping_sub(1)).and_then(|s| s.get(t.1)) {
+            Some(transaction) if transaction.success => transaction,
+            _ => return Err(CertificateError::UnknownTransaction(t)),
+        };
+
+        if commit {
+            let (own, start) = match pending.remove(&t) {
+                Some(writes) => writes,
+                None if committed.contains(&t) => return Err(CertificateError::Repeated(t)),
+                None => return Err(CertificateError::CommitBeforeStart(t)),
+            };
+            for (&x, &value) in own.iter() {
+                if let Some(&(_, other, position)) = store.get(&x) {
+                    if snapshot_isolation && position > start {
+                        return Err(CertificateError::ConcurrentWrites {
+                            variable: x,
+                            first: other,
+                            second: t,
+                        });
+                    }
+                }
+                store.insert(x, (value, t, i));
+            }
+            committed.insert(t);
+            continue;
+        }
+
+        if pending.contains_key(&t) || committed.contains(&t) {
+            return Err(CertificateError::Repeated(t));
+        }
+        if let Some(before) = previous_committed(histories, t) {
+            if !committed.contains(&before) {
+                return Err(CertificateError::SessionOrder { before, after: t });
+            }
+        }
+
+        // reads see the snapshot at the start, overlaid by the own writes
+        let mut own: HashMap<Variable, usize> = HashMap::new();
+        for (i_event, event) in transaction.events.iter().enumerate() {
+            if !event.success {
+                continue;
+            }
+            if event.write {
+                own.insert(event.variable, event.value);
+            } else {
+                let expected = match own.get(&event.variable) {
+                    Some(&value) => value,
+                    None => store.get(&event.variable).map_or(0, |&(value, _, _)| value),
+                };
+                if event.value != expected {
+                    return Err(CertificateError::WrongRead {
+                        read: (t.0, t.1, i_event),
+                        value: event.value,
+                        expected,
+                    });
+                }
+            }
+        }
+        pending.insert(t, (own, i));
+    }
+
+    // transactions without a successful event change nothing
+    for (i_node, session) in histories.iter().enumerate() {
+        for (i_transaction, transaction) in session.iter().enumerate() {
+            let t = (i_node + 1, i_transaction);
+            if transaction.success
+                && transaction.events.iter().any(|e| e.success)
+                && !committed.contains(&t)
+            {
+                return Err(CertificateError::Missing(t));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the closest committed predecessor in the session that did something
+fn previous_committed(histories: &[Session], t: TransactionId) -> Option<TransactionId> {
+    histories[t.0 - 1][..t.1]
+        .iter()
+        .rposition(|transaction| {
+            transaction.success && transaction.events.iter().any(|e| e.success)
+        })
+        .map(|i| (t.0, i))
+}
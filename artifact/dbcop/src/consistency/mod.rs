@@ -1,8 +1,13 @@
 pub mod algo;
+pub mod cdcl;
+pub mod certificate;
+pub mod polygraph;
 pub mod sat;
+pub mod solver;
 pub mod util;
+pub mod witness;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Consistency {
     ReadCommitted,
     RepeatableRead,
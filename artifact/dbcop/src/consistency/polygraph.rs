@@ -0,0 +1,375 @@
+use hashbrown::{HashMap, HashSet};
+
+use consistency::solver::{Clause, SatBackend, SatError};
+use consistency::util::{Budget, DiGraph, OutOfBudget, Reachability};
+use consistency::witness::{CommitOrder, EdgeKind, Witness, WitnessEdge};
+
+use slog::Logger;
+
+type TransactionId = (usize, usize);
+type TransactionInfo = (HashMap<usize, TransactionId>, HashSet<usize>);
+type Variable = usize;
+
+// one order between two writers of a variable, with the anti-dependencies it implies
+type Side = Vec<WitnessEdge>;
+
+// known so, wr edges and the ww, rw edges of the initial writes, plus a
+// constraint for every other pair of writers of the same variable
+#[derive(Debug)]
+pub struct Polygraph {
+    snapshot_isolation: bool,
+    transactions: Vec<TransactionId>,
+    known: Vec<WitnessEdge>,
+    constraints: Vec<(Side, Side)>,
+    log: Logger,
+}
+
+impl Polygraph {
+    pub fn new(
+        txns_info: &HashMap<TransactionId, TransactionInfo>,
+        snapshot_isolation: bool,
+        log: Logger,
+    ) -> Self {
+        let mut known = Vec::new();
+        let mut constraints = Vec::new();
+
+        let mut transactions: Vec<_> = txns_info.keys().cloned().collect();
+        transactions.sort_unstable();
+
+        for ts in transactions.windows(2) {
+            let prev = if ts[0].0 == ts[1].0 { ts[0] } else { (0, 0) };
+            known.push((prev, EdgeKind::SO, ts[1]));
+        }
+
+        let mut writers: HashMap<Variable, Vec<TransactionId>> = Default::default();
+        let mut readers: HashMap<(Variable, TransactionId), Vec<TransactionId>> =
+            Default::default();
+
+        for &txn_id in transactions.iter() {
+            let (read_info, write_info) = &txns_info[&txn_id];
+            for &x in write_info.iter() {
+                writers.entry(x).or_insert_with(Vec::new).push(txn_id);
+            }
+            for (&x, &writer) in read_info.iter() {
+                known.push((writer, EdgeKind::WR(x), txn_id));
+                readers
+                    .entry((x, writer))
+                    .or_insert_with(Vec::new)
+                    .push(txn_id);
+            }
+        }
+
+        let mut variables: Vec<_> = writers.keys().cloned().collect();
+        variables.sort_unstable();
+
+        for x in variables {
+            let writers_x = &writers[&x];
+            // readers of t1 come before every later writer t2
+            let order = |t1: TransactionId, t2: TransactionId| {
+                let mut side = vec![(t1, EdgeKind::WW(x), t2)];
+                if let Some(readers_x) = readers.get(&(x, t1)) {
+                    side.extend(
+                        readers_x
+                            .iter()
+                            .filter(|&&r| r != t2)
+                            .map(|&r| (r, EdgeKind::RW(x), t2)),
+                    );
+                }
+                side
+            };
+            for (i, &t1) in writers_x.iter().enumerate() {
+                for &t2 in writers_x[i + 1..].iter() {
+                    if t1 == (0, 0) {
+                        // the initial write comes first
+                        known.extend(order(t1, t2));
+                    } else {
+                        constraints.push((order(t1, t2), order(t2, t1)));
+                    }
+                }
+            }
+        }
+
+        Polygraph {
+            snapshot_isolation,
+            transactions,
+            known,
+            constraints,
+            log,
+        }
+    }
+
+    fn is_dependency(kind: EdgeKind) -> bool {
+        match kind {
+            EdgeKind::RW(_) => false,
+            _ => true,
+        }
+    }
+
+    // the graph to be acyclic, with the edges each of its edges stands for:
+    // dep and rw for serializability, dep and dep;rw for snapshot isolation
+    fn induced(
+        &self,
+        edges: &[WitnessEdge],
+    ) -> HashMap<(TransactionId, TransactionId), Vec<WitnessEdge>> {
+        let mut graph: HashMap<_, Vec<WitnessEdge>> = HashMap::new();
+
+        if !self.snapshot_isolation {
+            for &edge in edges.iter() {
+                graph.entry((edge.0, edge.2)).or_insert_with(|| vec![edge]);
+            }
+            return graph;
+        }
+
+        let mut rw_out: HashMap<TransactionId, Vec<WitnessEdge>> = HashMap::new();
+        for &edge in edges.iter().filter(|e| !Self::is_dependency(e.1)) {
+            rw_out.entry(edge.0).or_insert_with(Vec::new).push(edge);
+        }
+
+        for &dep in edges.iter().filter(|e| Self::is_dependency(e.1)) {
+            graph.entry((dep.0, dep.2)).or_insert_with(|| vec![dep]);
+            if let Some(rws) = rw_out.get(&dep.2) {
+                for &rw in rws.iter() {
+                    graph.entry((dep.0, rw.2)).or_insert_with(|| vec![dep, rw]);
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn digraph(
+        graph: &HashMap<(TransactionId, TransactionId), Vec<WitnessEdge>>,
+    ) -> DiGraph<TransactionId> {
+        let mut digraph: DiGraph<TransactionId> = Default::default();
+        for &(u, v) in graph.keys() {
+            digraph.add_edge(u, v);
+        }
+        digraph
+    }
+
+    fn expand(
+        cycle: &[TransactionId],
+        graph: &HashMap<(TransactionId, TransactionId), Vec<WitnessEdge>>,
+    ) -> Vec<WitnessEdge> {
+        (0..cycle.len())
+            .flat_map(|i| graph[&(cycle[i], cycle[(i + 1) % cycle.len()])].clone())
+            .collect()
+    }
+
+    // whether the edges of `side` close no cycle with the known graph
+    fn fits(
+        &self,
+        side: &[WitnessEdge],
+        reach: &Reachability<TransactionId>,
+        dep_in: &HashMap<TransactionId, Vec<TransactionId>>,
+        rw_out: &HashMap<TransactionId, Vec<TransactionId>>,
+    ) -> bool {
+        let acyclic = |u: TransactionId, v: TransactionId| u != v && !reach.reaches(&v, &u);
+        let none = Vec::new();
+
+        side.iter().all(|&(u, kind, v)| {
+            if !self.snapshot_isolation {
+                acyclic(u, v)
+            } else if Self::is_dependency(kind) {
+                acyclic(u, v)
+                    && rw_out
+                        .get(&v)
+                        .unwrap_or(&none)
+                        .iter()
+                        .all(|&w| acyclic(u, w))
+            } else {
+                dep_in
+                    .get(&u)
+                    .unwrap_or(&none)
+                    .iter()
+                    .all(|&t| acyclic(t, v))
+            }
+        })
+    }
+
+    // resolves constraints with a side that would close a cycle, until none is left
+    fn prune(&mut self, budget: &Budget) -> Result<Option<Vec<WitnessEdge>>, OutOfBudget> {
+        loop {
+            budget.check(0)?;
+
+            let graph = self.induced(&self.known);
+            let digraph = Self::digraph(&graph);
+
+            if let Some(cycle) = digraph.find_cycle() {
+                return Ok(Some(Self::expand(&cycle, &graph)));
+            }
+
+            let reach = digraph.reachability();
+
+            let mut dep_in: HashMap<TransactionId, Vec<TransactionId>> = HashMap::new();
+            let mut rw_out: HashMap<TransactionId, Vec<TransactionId>> = HashMap::new();
+            for &(u, kind, v) in self.known.iter() {
+                if Self::is_dependency(kind) {
+                    dep_in.entry(v).or_insert_with(Vec::new).push(u);
+                } else {
+                    rw_out.entry(u).or_insert_with(Vec::new).push(v);
+                }
+            }
+
+            let mut changed = false;
+            let mut remaining = Vec::new();
+
+            for (either, or) in std::mem::replace(&mut self.constraints, Vec::new()) {
+                match (
+                    self.fits(&either, &reach, &dep_in, &rw_out),
+                    self.fits(&or, &reach, &dep_in, &rw_out),
+                ) {
+                    (true, true) => remaining.push((either, or)),
+                    // if neither fits, the cycle is found in the next round
+                    (_, false) => {
+                        self.known.extend(either);
+                        changed = true;
+                    }
+                    (false, true) => {
+                        self.known.extend(or);
+                        changed = true;
+                    }
+                }
+            }
+
+            self.constraints = remaining;
+
+            if !changed {
+                return Ok(None);
+            }
+        }
+    }
+
+    // `None` if an acyclic choice exists, otherwise a cycle: one left after
+    // pruning, or the one of the last choice tried
+    pub fn check(
+        &mut self,
+        backend: &dyn SatBackend,
+        budget: &Budget,
+    ) -> Result<Option<Witness>, SatError> {
+        let n_constraint = self.constraints.len();
+
+        if let Some(cycle) = self.prune(budget)? {
+            return Ok(Some(Witness::Cycle(cycle)));
+        }
+
+        info!(self.log, "pruned constraints";
+            "before" => n_constraint,
+            "after" => self.constraints.len(),
+        );
+
+        if self.constraints.is_empty() {
+            return Ok(None);
+        }
+
+        // variable i + 1 is true if the first side of constraint i is chosen
+        let mut origin: HashMap<WitnessEdge, (usize, bool)> = HashMap::new();
+        for (i, (either, or)) in self.constraints.iter().enumerate() {
+            for &edge in either.iter() {
+                origin.insert(edge, (i, true));
+            }
+            for &edge in or.iter() {
+                origin.insert(edge, (i, false));
+            }
+        }
+
+        // learns across the rounds, as every round only adds a clause
+        let mut solver = backend.incremental(self.constraints.len());
+        let mut last_cycle = None;
+
+        loop {
+            budget.check(0)?;
+
+            let assignments = match solver.solve(budget)? {
+                Some(assignments) => assignments,
+                None => {
+                    // the first round has no clause to be unsatisfiable with
+                    let cycle = last_cycle.expect("unsatisfiable before any cycle");
+                    return Ok(Some(Witness::Cycle(cycle)));
+                }
+            };
+
+            let mut edges = self.known.clone();
+            for (i, (either, or)) in self.constraints.iter().enumerate() {
+                edges.extend(if assignments[i + 1] { either } else { or });
+            }
+
+            let graph = self.induced(&edges);
+
+            match Self::digraph(&graph).find_cycle() {
+                None => {
+                    self.known = edges;
+                    self.constraints.clear();
+                    return Ok(None);
+                }
+                Some(cycle) => {
+                    let cycle = Self::expand(&cycle, &graph);
+                    // not every choice on this cycle at once
+                    let mut clause: Clause = cycle
+                        .iter()
+                        .filter_map(|edge| origin.get(edge))
+                        .map(|&(i, side)| (!side, i + 1))
+                        .collect();
+                    clause.sort_unstable();
+                    clause.dedup();
+                    assert!(!clause.is_empty(), "known graph is acyclic after pruning");
+                    solver.add_clause(&clause);
+                    last_cycle = Some(cycle);
+                }
+            }
+        }
+    }
+
+    // after a successful `check`, a topological order of the chosen graph; for
+    // snapshot isolation, dependencies end before their target starts and a
+    // transaction starts before its anti-dependencies commit
+    pub fn commit_order(&self) -> CommitOrder {
+        assert!(self.constraints.is_empty(), "constraints left unresolved");
+
+        // the initial writes are in session order edges, even if never read
+        let mut vertices: HashSet<TransactionId> = self.transactions.iter().cloned().collect();
+        for &(u, _, v) in self.known.iter() {
+            vertices.insert(u);
+            vertices.insert(v);
+        }
+
+        if !self.snapshot_isolation {
+            let mut digraph: DiGraph<TransactionId> = Default::default();
+            for &t in vertices.iter() {
+                digraph.add_vertex(t);
+            }
+            for &(u, _, v) in self.known.iter() {
+                digraph.add_edge(u, v);
+            }
+            return CommitOrder::Serial(Self::topological_order(&digraph));
+        }
+
+        let mut digraph: DiGraph<(TransactionId, bool)> = Default::default();
+        for &t in vertices.iter() {
+            digraph.add_edge((t, false), (t, true));
+        }
+        for &(u, kind, v) in self.known.iter() {
+            if Self::is_dependency(kind) {
+                digraph.add_edge((u, true), (v, false));
+            } else {
+                digraph.add_edge((u, false), (v, true));
+            }
+        }
+        CommitOrder::Snapshot(Self::topological_order(&digraph))
+    }
+
+    fn topological_order<T>(digraph: &DiGraph<T>) -> Vec<T>
+    where
+        T: std::hash::Hash + Eq + Ord + Copy + std::fmt::Debug,
+    {
+        digraph
+            .strongly_connected_components()
+            .into_iter()
+            .rev()
+            .map(|component| {
+                assert_eq!(component.len(), 1, "chosen graph is acyclic");
+                component[0]
+            })
+            .collect()
+    }
+}
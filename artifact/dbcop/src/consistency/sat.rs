@@ -1,17 +1,9 @@
 use hashbrown::{HashMap, HashSet};
-use std::fs;
-use std::fs::{File, OpenOptions};
 
 use std::default::Default;
 
-use std::process::{Command, Stdio};
-
-use std::path::PathBuf;
-
-use std::io::BufRead;
-use std::io::{BufReader, BufWriter};
-
-use std::io::Write;
+use consistency::solver::{Clause, SatBackend, SatError};
+use consistency::util::Budget;
 
 #[derive(Hash, Ord, PartialOrd, Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Edge {
@@ -21,7 +13,7 @@ pub enum Edge {
 
 #[derive(Debug)]
 struct CNF {
-    clauses: Vec<Vec<(bool, usize)>>,
+    clauses: Vec<Clause>,
     n_variable: usize,
 }
 
@@ -43,30 +35,6 @@ impl CNF {
     fn finish_clause(&mut self) {
         self.clauses.push(Vec::new());
     }
-
-    fn write_to_file(&mut self, path: &PathBuf) {
-        let mut file = BufWriter::new(
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(path)
-                .expect("couldn't create"),
-        );
-
-        writeln!(file, "p cnf {} {}", self.n_variable, self.clauses.len() - 1)
-            .expect("failed to write parameters");
-        for clause in self.clauses.drain(..).rev().skip(1) {
-            for (sign, literal) in clause {
-                if sign {
-                    write!(file, "{} ", literal).expect("failed to write cnf to file");
-                } else {
-                    write!(file, "-{} ", literal).expect("failed to write cnf to file");
-                }
-            }
-            writeln!(file, "0").expect("failed to write cnf to file");
-        }
-    }
 }
 
 #[derive(Debug)]
@@ -75,6 +43,7 @@ pub struct Sat {
     edge_variable: HashMap<(Edge, (usize, usize), (usize, usize)), usize>,
     write_variable: HashMap<usize, HashMap<(usize, usize), HashSet<(usize, usize)>>>,
     transactions: Vec<(usize, usize)>,
+    assignments: Vec<bool>,
 }
 
 impl Sat {
@@ -100,7 +69,11 @@ impl Sat {
             wr_map.entry((0, 0)).or_insert_with(Default::default);
         }
 
+        // the initial writes are ordered with the others, even if never read
         let mut transactions: Vec<_> = txns_info.keys().cloned().collect();
+        if !txns_info.contains_key(&(0, 0)) {
+            transactions.push((0, 0));
+        }
         transactions.sort_unstable();
 
         Sat {
@@ -108,9 +81,14 @@ impl Sat {
             edge_variable: HashMap::new(),
             write_variable,
             transactions,
+            assignments: Vec::new(),
         }
     }
 
+    pub fn transactions(&self) -> &[(usize, usize)] {
+        &self.transactions
+    }
+
     pub fn session(&mut self) {
         let mut clauses = Vec::new();
 
@@ -262,66 +240,29 @@ impl Sat {
         self.add_clauses(&clauses);
     }
 
-    pub fn solve(&mut self, path: &PathBuf) -> Option<Vec<(usize, usize)>> {
-        let inp_cnf = path.join("history.cnf");
-        let out_cnf = path.join("result.cnf");
-        self.cnf.write_to_file(&inp_cnf);
+    pub fn solve(
+        &mut self,
+        backend: &dyn SatBackend,
+        budget: &Budget,
+    ) -> Result<Option<Vec<(usize, usize)>>, SatError> {
+        let n_clause = self.cnf.clauses.len() - 1;
+        let assignments =
+            match backend.solve(self.cnf.n_variable, &self.cnf.clauses[..n_clause], budget)? {
+                Some(assignments) => assignments,
+                None => return Ok(None),
+            };
+
+        self.assignments = assignments;
 
-        if let Ok(mut child) = Command::new("minisat")
-            .arg(&inp_cnf)
-            .arg(&out_cnf)
-            .stdout(Stdio::null())
-            .spawn()
         {
-            child.wait().expect("failed to execute process");
-        } else {
-            panic!("failed to execute process")
-        }
-
-        fs::remove_file(inp_cnf).expect("couldn't delete input cnf");
-
-        // println!("status: {}", output.status);
-        // println!("stdout: {}", String::from_utf8_lossy(&output.stdout));
-        // println!("stderr: {}", String::from_utf8_lossy(&output.stderr));
-
-        let result = File::open(&out_cnf).expect("file couldn't open");
-
-        let reader = BufReader::new(&result);
-
-        let mut lines = reader.lines().map(|l| l.unwrap());
-
-        let mut assignments = HashMap::new();
-
-        match lines.next() {
-            Some(ref e) if e.as_str() == "SAT" => {
-                for line in lines {
-                    for var_st in line.split_whitespace() {
-                        let var: isize = var_st.parse().unwrap();
-                        if var != 0 {
-                            assignments.insert(var.abs() as usize, var > 0);
-                        }
-                    }
-                }
-            }
-            Some(ref e) if e.as_str() == "UNSAT" => {
-                // println!("{:?}", e);
-                // for line in lines {
-                //     println!("{}", line);
-                // }
-            }
-            _ => {
-                unreachable!();
-            }
-        }
-
-        if !assignments.is_empty() {
+            let assignments = &self.assignments;
             let edges: Vec<_> = self
                 .edge_variable
                 .iter()
                 .filter_map(|(&k, &v)| {
                     if k.0 == Edge::CO {
                         assert!(k.1 != k.2);
-                        Some(if assignments[&v] {
+                        Some(if assignments[v] {
                             (k.1, k.2)
                         } else {
                             (k.2, k.1)
@@ -363,12 +304,39 @@ impl Sat {
                 lin.push(next_t[0]);
             }
 
-            Some(lin)
-        } else {
-            None
+            Ok(Some(lin))
         }
     }
 
+    // start and commit points for the commit order `co` of the last model: each
+    // transaction starts right after the last commit visible to it
+    pub fn snapshots(&self, co: &[(usize, usize)]) -> Vec<((usize, usize), bool)> {
+        let position: HashMap<_, _> = co.iter().enumerate().map(|(i, &t)| (t, i)).collect();
+
+        let mut last_visible: HashMap<(usize, usize), usize> = Default::default();
+        for (&(edge, u, v), &variable) in self.edge_variable.iter() {
+            if edge == Edge::VI && self.assignments[variable] {
+                let entry = last_visible.entry(v).or_insert(0);
+                *entry = std::cmp::max(*entry, position[&u] + 1);
+            }
+        }
+
+        let mut starts: Vec<Vec<(usize, usize)>> = vec![Vec::new(); co.len() + 1];
+        for &t in co.iter() {
+            starts[last_visible.get(&t).cloned().unwrap_or(0)].push(t);
+        }
+
+        let mut order = Vec::new();
+        for (i, starts_i) in starts.into_iter().enumerate() {
+            if i > 0 {
+                order.push((co[i - 1], true));
+            }
+            order.extend(starts_i.into_iter().map(|t| (t, false)));
+        }
+
+        order
+    }
+
     pub fn add_clause(&mut self, edges: &[(Edge, (usize, usize), (usize, usize), bool)]) {
         for edge in edges.iter() {
             let (variable, flip) = self.get_variable(edge.0, edge.1, edge.2);
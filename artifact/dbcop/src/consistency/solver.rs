@@ -0,0 +1,287 @@
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use consistency::cdcl::CdclSolver;
+use consistency::util::{Budget, OutOfBudget};
+
+// distinct file names for concurrent solver calls in the same directory
+static N_CALL: AtomicUsize = AtomicUsize::new(0);
+
+// (sign, variable), variables are numbered from 1
+pub type Clause = Vec<(bool, usize)>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SatError {
+    Timeout,
+    // the budget of the verification was cancelled
+    Cancelled,
+    // the solver could not be started
+    Unavailable(String),
+    // the solver finished, but its answer could not be read
+    Unexpected(String),
+}
+
+impl fmt::Display for SatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SatError::Timeout => write!(f, "sat solver timed out"),
+            SatError::Cancelled => write!(f, "sat solver cancelled"),
+            SatError::Unavailable(e) => write!(f, "sat solver unavailable - {}", e),
+            SatError::Unexpected(e) => write!(f, "unexpected sat solver output - {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SatError {}
+
+// shared by the components verified in parallel
+pub trait SatBackend: Send + Sync {
+    // `Ok(None)` if unsatisfiable, otherwise the value of every variable,
+    // indexed from 1; gives up past the deadline of `budget` or when it is
+    // cancelled, as well as past the timeout of the backend
+    fn solve(
+        &self,
+        n_variable: usize,
+        clauses: &[Clause],
+        budget: &Budget,
+    ) -> Result<Option<Vec<bool>>, SatError>;
+
+    // a solver of `n_variable` variables to add clauses to between calls, as
+    // when refining an abstraction
+    fn incremental<'a>(&'a self, n_variable: usize) -> Box<dyn IncrementalSat + 'a>;
+}
+
+pub trait IncrementalSat {
+    fn add_clause(&mut self, clause: &[(bool, usize)]);
+
+    // as `SatBackend::solve`, on every clause added so far
+    fn solve(&mut self, budget: &Budget) -> Result<Option<Vec<bool>>, SatError>;
+}
+
+// solves every clause added so far from scratch, for backends that keep
+// nothing between calls
+pub struct Resolve<'a> {
+    backend: &'a dyn SatBackend,
+    n_variable: usize,
+    clauses: Vec<Clause>,
+}
+
+impl<'a> Resolve<'a> {
+    pub fn new(backend: &'a dyn SatBackend, n_variable: usize) -> Self {
+        Resolve {
+            backend,
+            n_variable,
+            clauses: Vec::new(),
+        }
+    }
+}
+
+impl<'a> IncrementalSat for Resolve<'a> {
+    fn add_clause(&mut self, clause: &[(bool, usize)]) {
+        self.clauses.push(clause.to_vec());
+    }
+
+    fn solve(&mut self, budget: &Budget) -> Result<Option<Vec<bool>>, SatError> {
+        self.backend.solve(self.n_variable, &self.clauses, budget)
+    }
+}
+
+// `budget` with its deadline brought forward to `timeout` from now
+pub fn within(budget: &Budget, timeout: Option<Duration>) -> Budget {
+    let deadline = match (budget.deadline, timeout.map(|t| Instant::now() + t)) {
+        (Some(d1), Some(d2)) => Some(std::cmp::min(d1, d2)),
+        (d1, d2) => d1.or(d2),
+    };
+    Budget {
+        deadline,
+        ..budget.clone()
+    }
+}
+
+impl From<OutOfBudget> for SatError {
+    fn from(e: OutOfBudget) -> Self {
+        match e {
+            OutOfBudget::Cancelled => SatError::Cancelled,
+            _ => SatError::Timeout,
+        }
+    }
+}
+
+// `cdcl` for the in-process solver, otherwise the name or path of a DIMACS solver binary
+pub fn backend_from_name(name: &str, dir: &Path, timeout: Option<Duration>) -> Box<dyn SatBackend> {
+    match name {
+        "cdcl" => Box::new(CdclSolver::new(timeout)),
+        binary => Box::new(DimacsSolver::new(binary, dir.to_path_buf(), timeout)),
+    }
+}
+
+// runs an external solver on a DIMACS file; `minisat` writes its model to a
+// result file, others (kissat, cadical, ...) print `s`/`v` lines to stdout
+#[derive(Debug, Clone)]
+pub struct DimacsSolver {
+    binary: PathBuf,
+    dir: PathBuf,
+    timeout: Option<Duration>,
+}
+
+impl DimacsSolver {
+    pub fn new<P: Into<PathBuf>>(binary: P, dir: PathBuf, timeout: Option<Duration>) -> Self {
+        DimacsSolver {
+            binary: binary.into(),
+            dir,
+            timeout,
+        }
+    }
+
+    fn is_minisat(&self) -> bool {
+        self.binary
+            .file_stem()
+            .map_or(false, |stem| stem.to_string_lossy().starts_with("minisat"))
+    }
+
+    fn write_cnf(path: &Path, n_variable: usize, clauses: &[Clause]) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        writeln!(file, "p cnf {} {}", n_variable, clauses.len())?;
+        for clause in clauses.iter() {
+            for &(sign, literal) in clause.iter() {
+                if sign {
+                    write!(file, "{} ", literal)?;
+                } else {
+                    write!(file, "-{} ", literal)?;
+                }
+            }
+            writeln!(file, "0")?;
+        }
+
+        Ok(())
+    }
+
+    // reads `SAT`/`UNSAT` or `s ...` status lines, followed by the model
+    fn parse_output<R: BufRead>(
+        reader: R,
+        n_variable: usize,
+    ) -> Result<Option<Vec<bool>>, SatError> {
+        let mut status = None;
+        let mut assignments = vec![false; n_variable + 1];
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| SatError::Unexpected(e.to_string()))?;
+            let line = line.trim();
+            let literals = match line {
+                "SAT" | "s SATISFIABLE" => {
+                    status = Some(true);
+                    continue;
+                }
+                "UNSAT" | "s UNSATISFIABLE" => {
+                    status = Some(false);
+                    continue;
+                }
+                _ if line.starts_with("v ") => &line[2..],
+                _ if status == Some(true) && !line.starts_with('c') => line,
+                _ => continue,
+            };
+            for var_st in literals.split_whitespace() {
+                let var: isize = var_st
+                    .parse()
+                    .map_err(|_| SatError::Unexpected(format!("bad literal {}", var_st)))?;
+                if var != 0 && (var.abs() as usize) <= n_variable {
+                    assignments[var.abs() as usize] = var > 0;
+                }
+            }
+        }
+
+        match status {
+            Some(true) => Ok(Some(assignments)),
+            Some(false) => Ok(None),
+            None => Err(SatError::Unexpected("no SAT or UNSAT answer".to_string())),
+        }
+    }
+}
+
+impl SatBackend for DimacsSolver {
+    fn solve(
+        &self,
+        n_variable: usize,
+        clauses: &[Clause],
+        budget: &Budget,
+    ) -> Result<Option<Vec<bool>>, SatError> {
+        let budget = within(budget, self.timeout);
+        let i_call = N_CALL.fetch_add(1, Ordering::Relaxed);
+        let inp_cnf = self.dir.join(format!("history-{}.cnf", i_call));
+        let out_cnf = self.dir.join(format!("result-{}.cnf", i_call));
+        Self::write_cnf(&inp_cnf, n_variable, clauses)
+            .map_err(|e| SatError::Unavailable(format!("couldn't write cnf - {}", e)))?;
+
+        let mut command = Command::new(&self.binary);
+        command.arg(&inp_cnf);
+        if self.is_minisat() {
+            command.arg(&out_cnf).stdout(Stdio::null());
+        } else {
+            command.stdout(Stdio::piped());
+        }
+
+        let mut child = command
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| SatError::Unavailable(format!("{} - {}", self.binary.display(), e)))?;
+
+        // read stdout while waiting, so that a large model can't block the solver
+        let stdout_reader = child.stdout.take().map(|mut stdout| {
+            thread::spawn(move || {
+                let mut output = Vec::new();
+                stdout.read_to_end(&mut output).map(|_| output)
+            })
+        });
+
+        let finished = loop {
+            match child.try_wait() {
+                Ok(Some(_)) => break Ok(()),
+                Ok(None) => match budget.check(0) {
+                    Ok(_) => thread::sleep(Duration::from_millis(10)),
+                    Err(e) => {
+                        child.kill().ok();
+                        child.wait().ok();
+                        break Err(e);
+                    }
+                },
+                Err(e) => return Err(SatError::Unexpected(e.to_string())),
+            }
+        };
+
+        fs::remove_file(&inp_cnf).ok();
+
+        let output = stdout_reader.map(|handle| handle.join());
+
+        if let Err(e) = finished {
+            fs::remove_file(&out_cnf).ok();
+            return Err(e.into());
+        }
+
+        match output {
+            Some(Ok(Ok(output))) => Self::parse_output(BufReader::new(&output[..]), n_variable),
+            Some(_) => Err(SatError::Unexpected(
+                "couldn't read solver output".to_string(),
+            )),
+            None => {
+                let result = File::open(&out_cnf)
+                    .map_err(|e| SatError::Unexpected(format!("no result file - {}", e)))?;
+                let decision = Self::parse_output(BufReader::new(result), n_variable);
+                fs::remove_file(&out_cnf).ok();
+                decision
+            }
+        }
+    }
+
+    // external solvers start over on every call
+    fn incremental<'a>(&'a self, n_variable: usize) -> Box<dyn IncrementalSat + 'a> {
+        Box::new(Resolve::new(self, n_variable))
+    }
+}
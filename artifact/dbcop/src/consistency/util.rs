@@ -8,6 +8,10 @@ use std::collections::VecDeque;
 
 use std::collections::BTreeSet;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
 #[derive(Default, Debug, Clone)]
 pub struct DiGraph<T>
 where
@@ -43,47 +47,136 @@ where
     }
 
     pub fn has_cycle(&self) -> bool {
-        self.adj_map.keys().any(|u| {
-            let mut reachable: HashSet<T> = Default::default();
-            self.dfs_util_reach(u, u, &mut reachable)
-        })
+        let dense = self.dense();
+        dense
+            .components()
+            .iter()
+            .any(|component| dense.is_cyclic(component))
     }
 
-    fn dfs_util_reach(&self, s: &T, u: &T, reachable: &mut HashSet<T>) -> bool {
-        if let Some(vs) = self.adj_map.get(u) {
-            for &v in vs.iter() {
-                if &v == s || (reachable.insert(v) && self.dfs_util_reach(s, &v, reachable)) {
-                    return true;
-                }
-            }
-        }
-        false
+    pub fn find_cycle(&self) -> Option<Vec<T>> {
+        let dense = self.dense();
+        let component = dense
+            .components()
+            .into_iter()
+            .find(|component| dense.is_cyclic(component))?;
+
+        // any path back from a successor inside the component closes a cycle
+        let u = component[0];
+        let v = *dense.succ[u]
+            .iter()
+            .find(|w| component.contains(w))
+            .expect("cyclic component has an inner edge");
+
+        let path = self.find_path(&dense.vertices[v], &dense.vertices[u])?;
+        let mut cycle = vec![dense.vertices[u]];
+        cycle.extend_from_slice(&path[..path.len() - 1]);
+        Some(cycle)
     }
 
-    fn dfs_util_all(&self, u: &T, reachable: &mut HashSet<T>) {
-        if let Some(vs) = self.adj_map.get(u) {
-            for &v in vs.iter() {
-                if reachable.insert(v) {
-                    self.dfs_util_all(&v, reachable);
+    // strongly connected components, each one before the components reaching it
+    pub fn strongly_connected_components(&self) -> Vec<Vec<T>> {
+        let dense = self.dense();
+        dense
+            .components()
+            .iter()
+            .map(|component| component.iter().map(|&i| dense.vertices[i]).collect())
+            .collect()
+    }
+
+    // shortest path from s to t, both included
+    pub fn find_path(&self, s: &T, t: &T) -> Option<Vec<T>> {
+        let mut parent: HashMap<T, T> = Default::default();
+        let mut queue: VecDeque<T> = Default::default();
+        queue.push_back(*s);
+        while let Some(u) = queue.pop_front() {
+            if &u == t {
+                let mut path = vec![u];
+                while path.last().unwrap() != s {
+                    let v = parent[path.last().unwrap()];
+                    path.push(v);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if let Some(vs) = self.adj_map.get(&u) {
+                for &v in vs.iter() {
+                    if &v != s && !parent.contains_key(&v) {
+                        parent.insert(v, u);
+                        queue.push_back(v);
+                    }
                 }
             }
         }
+        None
     }
 
+    // each vertex with its reachable vertices; itself only if it is on a cycle
     pub fn take_closure(&self) -> Self {
+        let reachability = self.reachability();
         DiGraph {
             adj_map: self
                 .adj_map
                 .keys()
-                .map(|&u| {
-                    let mut reachable: HashSet<T> = Default::default();
-                    self.dfs_util_all(&u, &mut reachable);
-                    (u, reachable)
-                })
+                .map(|u| (*u, reachability.reachable_from(u).collect()))
                 .collect(),
         }
     }
 
+    pub fn reachability(&self) -> Reachability<T> {
+        let dense = self.dense();
+        let components = dense.components();
+
+        let mut component_of = vec![0; dense.vertices.len()];
+        for (i_component, component) in components.iter().enumerate() {
+            for &u in component.iter() {
+                component_of[u] = i_component;
+            }
+        }
+
+        // components come before the ones reaching them
+        let mut reach: Vec<BitSet> = Vec::with_capacity(components.len());
+        for (i_component, component) in components.iter().enumerate() {
+            let mut bits = BitSet::new(dense.vertices.len());
+            if dense.is_cyclic(component) {
+                for &u in component.iter() {
+                    bits.insert(u);
+                }
+            }
+            for &u in component.iter() {
+                for &v in dense.succ[u].iter() {
+                    bits.insert(v);
+                    if component_of[v] != i_component {
+                        bits.union_with(&reach[component_of[v]]);
+                    }
+                }
+            }
+            reach.push(bits);
+        }
+
+        Reachability {
+            dense,
+            component_of,
+            reach,
+        }
+    }
+
+    fn dense(&self) -> Dense<T> {
+        let mut dense = Dense {
+            vertices: Vec::new(),
+            index: HashMap::new(),
+            succ: Vec::new(),
+        };
+        for (&u, vs) in self.adj_map.iter() {
+            let i = dense.index_of(u);
+            for &v in vs.iter() {
+                let j = dense.index_of(v);
+                dense.succ[i].push(j);
+            }
+        }
+        dense
+    }
+
     pub fn union_with(&mut self, g: &Self) {
         for (&u, vs) in g.adj_map.iter() {
             let entry = self.adj_map.entry(u).or_insert_with(Default::default);
@@ -94,6 +187,212 @@ where
     }
 }
 
+// vertices mapped to contiguous indices
+#[derive(Debug, Clone)]
+struct Dense<T>
+where
+    T: Hash + Eq + Copy + Debug,
+{
+    vertices: Vec<T>,
+    index: HashMap<T, usize>,
+    succ: Vec<Vec<usize>>,
+}
+
+impl<T> Dense<T>
+where
+    T: Hash + Eq + Copy + Debug,
+{
+    fn index_of(&mut self, u: T) -> usize {
+        if let Some(&i) = self.index.get(&u) {
+            return i;
+        }
+        let i = self.vertices.len();
+        self.vertices.push(u);
+        self.succ.push(Vec::new());
+        self.index.insert(u, i);
+        i
+    }
+
+    fn is_cyclic(&self, component: &[usize]) -> bool {
+        component.len() > 1 || self.succ[component[0]].contains(&component[0])
+    }
+
+    // iterative Tarjan, components are found sinks first
+    fn components(&self) -> Vec<Vec<usize>> {
+        let n = self.vertices.len();
+        let mut index = vec![None; n];
+        let mut low_link = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+        let mut calls: Vec<(usize, usize)> = Vec::new();
+
+        for s in 0..n {
+            if index[s].is_some() {
+                continue;
+            }
+            index[s] = Some(next_index);
+            low_link[s] = next_index;
+            next_index += 1;
+            stack.push(s);
+            on_stack[s] = true;
+            calls.push((s, 0));
+
+            while let Some(&(u, i)) = calls.last() {
+                if i < self.succ[u].len() {
+                    calls.last_mut().unwrap().1 += 1;
+                    let v = self.succ[u][i];
+                    match index[v] {
+                        None => {
+                            index[v] = Some(next_index);
+                            low_link[v] = next_index;
+                            next_index += 1;
+                            stack.push(v);
+                            on_stack[v] = true;
+                            calls.push((v, 0));
+                        }
+                        Some(index_v) if on_stack[v] => {
+                            low_link[u] = std::cmp::min(low_link[u], index_v);
+                        }
+                        _ => {}
+                    }
+                } else {
+                    calls.pop();
+                    if let Some(&(parent, _)) = calls.last() {
+                        low_link[parent] = std::cmp::min(low_link[parent], low_link[u]);
+                    }
+                    if Some(low_link[u]) == index[u] {
+                        let mut component = Vec::new();
+                        loop {
+                            let v = stack.pop().unwrap();
+                            on_stack[v] = false;
+                            component.push(v);
+                            if v == u {
+                                break;
+                            }
+                        }
+                        components.push(component);
+                    }
+                }
+            }
+        }
+
+        components
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(n: usize) -> Self {
+        BitSet {
+            words: vec![0; (n + 63) / 64],
+        }
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= *other_word;
+        }
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| 64 * i + bit)
+        })
+    }
+}
+
+// transitive closure as one bitset per strongly connected component
+#[derive(Debug, Clone)]
+pub struct Reachability<T>
+where
+    T: Hash + Eq + Copy + Debug,
+{
+    dense: Dense<T>,
+    component_of: Vec<usize>,
+    reach: Vec<BitSet>,
+}
+
+impl<T> Reachability<T>
+where
+    T: Hash + Eq + Copy + Debug,
+{
+    // a non-empty path from u to v
+    pub fn reaches(&self, u: &T, v: &T) -> bool {
+        match (self.dense.index.get(u), self.dense.index.get(v)) {
+            (Some(&i), Some(&j)) => self.reach[self.component_of[i]].contains(j),
+            _ => false,
+        }
+    }
+
+    pub fn reachable_from<'a>(&'a self, u: &T) -> impl Iterator<Item = T> + 'a {
+        self.dense
+            .index
+            .get(u)
+            .map(|&i| &self.reach[self.component_of[i]])
+            .into_iter()
+            .flat_map(|bits| bits.iter())
+            .map(move |j| self.dense.vertices[j])
+    }
+}
+
+// limits on a linearization search: a wall-clock deadline and the number of
+// explored states kept in memory
+#[derive(Default, Debug, Clone)]
+pub struct Budget {
+    pub deadline: Option<Instant>,
+    pub max_states: Option<usize>,
+    // set when the search is no longer needed, e.g. another component failed
+    pub cancelled: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfBudget {
+    Timeout,
+    States,
+    Cancelled,
+}
+
+impl Budget {
+    pub fn check(&self, n_state: usize) -> Result<(), OutOfBudget> {
+        if self.max_states.map_or(false, |max| n_state > max) {
+            Err(OutOfBudget::States)
+        } else if self.deadline.map_or(false, |d| Instant::now() >= d) {
+            Err(OutOfBudget::Timeout)
+        } else if self
+            .cancelled
+            .as_ref()
+            .map_or(false, |c| c.load(Ordering::Relaxed))
+        {
+            Err(OutOfBudget::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LinearizationError<V> {
+    // the deepest partial linearization explored
+    NotFound(Vec<V>),
+    OutOfBudget(OutOfBudget),
+}
+
 pub trait ConstrainedLinearization {
     type Vertex: Hash + Eq + Copy + Ord + Debug;
 
@@ -114,13 +413,19 @@ pub trait ConstrainedLinearization {
         active_parent: &mut HashMap<Self::Vertex, usize>,
         linearization: &mut Vec<Self::Vertex>,
         seen: &mut HashSet<BTreeSet<Self::Vertex>>,
-    ) -> bool {
+        longest: &mut Vec<Self::Vertex>,
+        budget: &Budget,
+    ) -> Result<bool, OutOfBudget> {
+        budget.check(seen.len())?;
+        if linearization.len() > longest.len() {
+            *longest = linearization.clone();
+        }
         if !seen.insert(non_det_choices.iter().cloned().collect()) {
             // seen is not modified
             // non-det choices are already explored
-            false
+            Ok(false)
         } else if non_det_choices.is_empty() {
-            true
+            Ok(true)
         } else {
             let curr_non_det_choices = non_det_choices.len();
             for _ in 0..curr_non_det_choices {
@@ -143,8 +448,15 @@ pub trait ConstrainedLinearization {
 
                         self.forward_book_keeping(linearization);
 
-                        if self.do_dfs(non_det_choices, active_parent, linearization, seen) {
-                            return true;
+                        if self.do_dfs(
+                            non_det_choices,
+                            active_parent,
+                            linearization,
+                            seen,
+                            longest,
+                            budget,
+                        )? {
+                            return Ok(true);
                         }
 
                         self.backtrack_book_keeping(linearization);
@@ -164,15 +476,23 @@ pub trait ConstrainedLinearization {
                     non_det_choices.push_back(u);
                 }
             }
-            false
+            Ok(false)
         }
     }
 
     fn get_linearization(&mut self) -> Option<Vec<Self::Vertex>> {
+        self.linearize(&Budget::default()).ok()
+    }
+
+    fn linearize(
+        &mut self,
+        budget: &Budget,
+    ) -> Result<Vec<Self::Vertex>, LinearizationError<Self::Vertex>> {
         let mut non_det_choices: VecDeque<Self::Vertex> = Default::default();
         let mut active_parent: HashMap<Self::Vertex, usize> = Default::default();
         let mut linearization: Vec<Self::Vertex> = Default::default();
         let mut seen: HashSet<BTreeSet<Self::Vertex>> = Default::default();
+        let mut longest: Vec<Self::Vertex> = Default::default();
 
         // do active_parent counting
         for u in self.vertices() {
@@ -194,17 +514,17 @@ pub trait ConstrainedLinearization {
             }
         });
 
-        self.do_dfs(
+        match self.do_dfs(
             &mut non_det_choices,
             &mut active_parent,
             &mut linearization,
             &mut seen,
-        );
-
-        if linearization.is_empty() {
-            None
-        } else {
-            Some(linearization)
+            &mut longest,
+            budget,
+        ) {
+            Ok(true) => Ok(linearization),
+            Ok(false) => Err(LinearizationError::NotFound(longest)),
+            Err(e) => Err(LinearizationError::OutOfBudget(e)),
         }
     }
 }
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use consistency::solver::SatError;
+use consistency::Consistency;
+
+type TransactionId = (usize, usize);
+type EventId = (usize, usize, usize);
+type Variable = usize;
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum EdgeKind {
+    SO,
+    WR(Variable),
+    WW(Variable),
+    RW(Variable),
+}
+
+pub type WitnessEdge = (TransactionId, EdgeKind, TransactionId);
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EdgeKind::SO => write!(f, "so"),
+            EdgeKind::WR(x) => write!(f, "wr({})", x),
+            EdgeKind::WW(x) => write!(f, "ww({})", x),
+            EdgeKind::RW(x) => write!(f, "rw({})", x),
+        }
+    }
+}
+
+// graphviz digraph of `edges`, drawing the `highlight` edges in bold red
+pub fn to_dot(edges: &[WitnessEdge], highlight: &[WitnessEdge]) -> String {
+    let mut dot = String::from("digraph counterexample {\n");
+
+    let mut vertices: Vec<_> = edges
+        .iter()
+        .chain(highlight.iter())
+        .flat_map(|&(u, _, v)| vec![u, v])
+        .collect();
+    vertices.sort_unstable();
+    vertices.dedup();
+
+    for (s, t) in vertices {
+        dot.push_str(&format!("  \"{}_{}\" [label=\"({}, {})\"];\n", s, t, s, t));
+    }
+
+    for edge in edges
+        .iter()
+        .chain(highlight.iter().filter(|e| !edges.contains(e)))
+    {
+        let &((s1, t1), kind, (s2, t2)) = edge;
+        let style = match kind {
+            EdgeKind::SO => "",
+            EdgeKind::WR(_) => ", color=blue",
+            EdgeKind::WW(_) => ", color=darkgreen",
+            EdgeKind::RW(_) => ", style=dashed",
+        };
+        let bold = if highlight.contains(edge) {
+            ", penwidth=2, fontcolor=red, color=red"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "  \"{}_{}\" -> \"{}_{}\" [label=\"{}\"{}{}];\n",
+            s1, t1, s2, t2, kind, style, bold
+        ));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Witness {
+    // read a value that no transaction wrote
+    ThinAirRead {
+        read: EventId,
+        variable: Variable,
+        value: usize,
+    },
+    // read from an aborted transaction
+    AbortedRead {
+        read: EventId,
+        write: EventId,
+    },
+    // read a non-final write of another transaction
+    IntermediateRead {
+        read: EventId,
+        write: EventId,
+    },
+    // did not read the latest write within the same transaction
+    LostUpdate {
+        read: EventId,
+        write: EventId,
+    },
+    // read a different write than the previous read of the same variable
+    NonRepeatableRead {
+        read: EventId,
+        write: EventId,
+        previous_write: EventId,
+    },
+    // read a write that it sees overwritten by `overwrite`, so that its
+    // anti-dependency on `overwrite` closes a cycle with visibility; found
+    // online, where the transactions on the way may be collected already
+    OverwrittenRead {
+        read: EventId,
+        write: EventId,
+        overwrite: TransactionId,
+    },
+    // cycle in the dependency graph, as (from, kind, to) edges
+    Cycle(Vec<WitnessEdge>),
+    // transactions left over by the deepest partial linearization
+    NonLinearizable(Vec<TransactionId>),
+}
+
+// why a history passed: a serial order of the transactions, or an order of
+// their start (false) and commit (true) points when reads come from snapshots
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitOrder {
+    Serial(Vec<TransactionId>),
+    Snapshot(Vec<(TransactionId, bool)>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Violation {
+    pub level: Consistency,
+    pub witness: Witness,
+    // sessions of the biconnected component the violation was found in
+    #[serde(default)]
+    pub sessions: Option<Vec<usize>>,
+    // edges around the witness, written as the counterexample graph
+    #[serde(skip)]
+    pub context: Vec<WitnessEdge>,
+}
+
+impl Violation {
+    pub fn new(level: Consistency, witness: Witness) -> Self {
+        Violation {
+            level,
+            witness,
+            sessions: None,
+            context: Vec::new(),
+        }
+    }
+}
+
+// Adya-style categories, with the read-level checks that have no Adya name
+#[derive(Hash, Ord, PartialOrd, Eq, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum AnomalyKind {
+    ThinAirRead,
+    G1a,
+    G1b,
+    LostUpdate,
+    NonRepeatableRead,
+    G1c,
+    GSingle,
+    G2,
+}
+
+impl AnomalyKind {
+    pub fn of(witness: &Witness) -> Option<Self> {
+        match witness {
+            Witness::ThinAirRead { .. } => Some(AnomalyKind::ThinAirRead),
+            Witness::AbortedRead { .. } => Some(AnomalyKind::G1a),
+            Witness::IntermediateRead { .. } => Some(AnomalyKind::G1b),
+            Witness::LostUpdate { .. } => Some(AnomalyKind::LostUpdate),
+            Witness::NonRepeatableRead { .. } => Some(AnomalyKind::NonRepeatableRead),
+            Witness::OverwrittenRead { .. } => Some(AnomalyKind::GSingle),
+            Witness::Cycle(edges) => {
+                match edges
+                    .iter()
+                    .filter(|(_, kind, _)| match kind {
+                        EdgeKind::RW(_) => true,
+                        _ => false,
+                    })
+                    .count()
+                {
+                    0 => Some(AnomalyKind::G1c),
+                    1 => Some(AnomalyKind::GSingle),
+                    _ => Some(AnomalyKind::G2),
+                }
+            }
+            Witness::NonLinearizable(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnomalyReport {
+    pub anomalies: Vec<(AnomalyKind, Witness)>,
+}
+
+impl AnomalyReport {
+    pub fn add(&mut self, witness: Witness) {
+        if let Some(kind) = AnomalyKind::of(&witness) {
+            self.anomalies.push((kind, witness));
+        }
+    }
+
+    pub fn counts(&self) -> BTreeMap<AnomalyKind, usize> {
+        let mut counts = BTreeMap::new();
+        for (kind, _) in self.anomalies.iter() {
+            *counts.entry(*kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum VerifyError {
+    // two writes of the same (variable, value); value 0 is the initial write
+    DuplicateWrite {
+        variable: Variable,
+        value: usize,
+        first: EventId,
+        second: EventId,
+    },
+    Violation(Violation),
+    // the sat backend gave no answer
+    Solver(SatError),
+    // a time or state limit ran out while checking `level`
+    Unknown {
+        level: Consistency,
+        reason: String,
+    },
+}
+
+impl VerifyError {
+    pub fn violation(&self) -> Option<&Violation> {
+        match self {
+            VerifyError::Violation(violation) => Some(violation),
+            _ => None,
+        }
+    }
+}
+
+impl From<Violation> for VerifyError {
+    fn from(violation: Violation) -> Self {
+        VerifyError::Violation(violation)
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::DuplicateWrite {
+                variable,
+                value,
+                first,
+                second,
+            } => write!(
+                f,
+                "malformed history - write <{}:{}> at {:?} and {:?}",
+                variable, value, first, second
+            ),
+            VerifyError::Violation(violation) => {
+                write!(
+                    f,
+                    "minimum level failed {:?}\nwitness: {:?}",
+                    violation.level, violation.witness
+                )?;
+                match violation.sessions {
+                    Some(ref sessions) => write!(f, "\nin component of sessions {:?}", sessions),
+                    None => Ok(()),
+                }
+            }
+            VerifyError::Solver(e) => write!(f, "{}", e),
+            VerifyError::Unknown { level, reason } => {
+                write!(f, "unknown ({}) while checking {:?}", reason, level)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
@@ -1,17 +1,18 @@
-use db::history::{HistParams, History, Session};
+use consistency::witness::VerifyError;
+use db::history::{HistParams, History, Session, Transaction};
+use verifier::online::OnlineVerifier;
 // use verifier::Verifier;
 
 // use std::collections::HashMap;
 
 use std::fs;
-use std::fs::File;
-use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
 use std::net::IpAddr;
 
 // use rand::distributions::{Distribution, Uniform};
 // use rand::Rng;
+use std::sync::mpsc;
 use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
@@ -26,8 +27,28 @@ pub struct Node {
     pub id: usize,
 }
 
+// what the session threads report while executing
+pub enum Progress {
+    // the 1-based session and the index of a finished transaction
+    Transaction((usize, usize), Transaction),
+    SessionDone(usize),
+}
+
 pub trait ClusterNode {
     fn exec_session(&self, hist: &mut Session);
+
+    // hands every transaction to `done` once it finished; by default, all of
+    // them after the session
+    fn exec_session_streaming(
+        &self,
+        hist: &mut Session,
+        done: &mut dyn FnMut(usize, &Transaction),
+    ) {
+        self.exec_session(hist);
+        for (i_transaction, transaction) in hist.iter().enumerate() {
+            done(i_transaction, transaction);
+        }
+    }
 }
 
 pub trait Cluster<N>
@@ -57,9 +78,7 @@ where
             .unwrap()
             .filter_map(|entry_res| match entry_res {
                 Ok(ref entry) if !&entry.path().is_dir() => {
-                    let file = File::open(entry.path()).unwrap();
-                    let buf_reader = BufReader::new(file);
-                    Some(bincode::deserialize_from(buf_reader).unwrap())
+                    Some(History::load(&entry.path()).unwrap())
                 }
                 _ => None,
             })
@@ -111,13 +130,99 @@ where
             exec,
         );
 
-        let file = File::create(dir.join("history.bincode")).unwrap();
-        let buf_writer = BufWriter::new(file);
-        bincode::serialize_into(buf_writer, &exec_hist).expect("dumping to bincode went wrong");
+        exec_hist
+            .save(&dir.join("history.bincode"))
+            .expect("dumping to bincode went wrong");
 
         None
     }
 
+    // like `execute`, verifying the transactions while they are executed;
+    // the first violation `online` reports
+    fn execute_online(
+        &mut self,
+        hist: &History,
+        dir: &Path,
+        online: &mut OnlineVerifier,
+    ) -> Result<(), VerifyError> {
+        self.setup();
+
+        self.setup_test(hist.get_params());
+
+        let mut exec = hist.get_cloned_data();
+
+        let start_time = chrono::Local::now();
+
+        let decision = self.exec_history_online(&mut exec, online);
+
+        let end_time = chrono::Local::now();
+
+        self.cleanup();
+
+        let exec_hist = History::new(
+            hist.get_cloned_params(),
+            self.info(),
+            start_time,
+            end_time,
+            exec,
+        );
+
+        exec_hist
+            .save(&dir.join("history.bincode"))
+            .expect("dumping to bincode went wrong");
+
+        decision
+    }
+
+    // the sessions keep running after a violation, but `online` sees no more
+    fn exec_history_online(
+        &self,
+        hist: &mut Vec<Session>,
+        online: &mut OnlineVerifier,
+    ) -> Result<(), VerifyError> {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut threads = (0..self.n_node())
+            .cycle()
+            .zip(hist.drain(..))
+            .enumerate()
+            .map(|(i_node, (node_id, mut single_hist))| {
+                let cluster_node = self.get_cluster_node(node_id);
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    cluster_node.exec_session_streaming(
+                        &mut single_hist,
+                        &mut |i_transaction, transaction| {
+                            sender
+                                .send(Progress::Transaction(
+                                    (i_node + 1, i_transaction),
+                                    transaction.clone(),
+                                ))
+                                .unwrap()
+                        },
+                    );
+                    sender.send(Progress::SessionDone(i_node + 1)).unwrap();
+                    single_hist
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        let mut decision = Ok(());
+        for progress in receiver.iter() {
+            if decision.is_ok() {
+                decision = match progress {
+                    Progress::Transaction(txn_id, transaction) => online.push(txn_id, transaction),
+                    Progress::SessionDone(i_node) => online.end_session(i_node),
+                };
+            }
+        }
+
+        hist.extend(threads.drain(..).map(|t| t.join().unwrap()));
+
+        decision.and_then(|_| online.finish())
+    }
+
     fn exec_history(&self, hist: &mut Vec<Session>) {
         let mut threads = (0..self.n_node())
             .cycle()
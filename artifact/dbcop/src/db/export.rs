@@ -0,0 +1,153 @@
+use hashbrown::HashMap;
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use db::history::{History, Session};
+use db::import::{ImportFormat, COBRA_INIT_TXN_ID, COBRA_INIT_WRITE_ID};
+
+// only successful events are exported; aborted transactions are exported to
+// cobra logs and edn, which have them, and dropped from text, where the reads
+// of their writes become reads from thin air
+pub fn export(hist: &History, path: &Path, format: ImportFormat) -> io::Result<()> {
+    match format {
+        ImportFormat::Text => to_text(hist.get_data(), BufWriter::new(File::create(path)?)),
+        ImportFormat::Cobra => to_cobra_logs(hist.get_data(), path),
+        ImportFormat::Elle => to_edn(hist.get_data(), BufWriter::new(File::create(path)?)),
+    }
+}
+
+// sessions and transactions are numbered from 1, in order
+pub fn to_text<W: Write>(histories: &[Session], mut writer: W) -> io::Result<()> {
+    let mut i_txn = 0;
+    for (i_node, session) in histories.iter().enumerate() {
+        for transaction in session.iter().filter(|transaction| transaction.success) {
+            i_txn += 1;
+            for event in transaction.events.iter().filter(|event| event.success) {
+                writeln!(
+                    writer,
+                    "{}({},{},{},{})",
+                    if event.write { 'w' } else { 'r' },
+                    event.variable,
+                    event.value,
+                    i_node + 1,
+                    i_txn
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn long2bytes(buf: &mut Vec<u8>, value: u64) {
+    for i in (0..8).rev() {
+        buf.push((value >> (8 * i)) as u8);
+    }
+}
+
+// one `T<session>.log` per session; write ids and transaction ids are numbered
+// from 1, a write id per (variable, value), so duplicate writes stay duplicates
+pub fn to_cobra_logs(histories: &[Session], dir: &Path) -> io::Result<()> {
+    if !dir.is_dir() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut write_ids: HashMap<(usize, usize), (u64, u64)> = HashMap::new();
+    let mut i_txn = 0;
+    for session in histories.iter() {
+        for transaction in session.iter() {
+            i_txn += 1;
+            for event in transaction.events.iter().filter(|event| event.write) {
+                let wid = write_ids.len() as u64 + 1;
+                write_ids
+                    .entry((event.variable, event.value))
+                    .or_insert((i_txn, wid));
+            }
+        }
+    }
+
+    // reads from nowhere read from a transaction and write ids no one has,
+    // as the null transaction stands for garbage collected writes
+    for session in histories.iter() {
+        for transaction in session.iter() {
+            for event in transaction.events.iter() {
+                if !event.write && event.value != 0 {
+                    let wid = write_ids.len() as u64 + 1;
+                    write_ids
+                        .entry((event.variable, event.value))
+                        .or_insert((i_txn + 1, wid));
+                }
+            }
+        }
+    }
+
+    let mut i_txn = 0;
+    for (i_node, session) in histories.iter().enumerate() {
+        let mut buf = Vec::new();
+        for transaction in session.iter() {
+            i_txn += 1;
+            buf.push(b'S');
+            long2bytes(&mut buf, i_txn);
+            for event in transaction.events.iter().filter(|event| event.success) {
+                if event.write {
+                    buf.push(b'W');
+                    long2bytes(&mut buf, write_ids[&(event.variable, event.value)].1);
+                    long2bytes(&mut buf, event.variable as u64);
+                    long2bytes(&mut buf, event.value as u64);
+                } else {
+                    let (w_txnid, w_wid) = if event.value == 0 {
+                        (COBRA_INIT_TXN_ID, COBRA_INIT_WRITE_ID)
+                    } else {
+                        write_ids[&(event.variable, event.value)]
+                    };
+                    buf.push(b'R');
+                    long2bytes(&mut buf, w_txnid);
+                    long2bytes(&mut buf, w_wid);
+                    long2bytes(&mut buf, event.variable as u64);
+                    long2bytes(&mut buf, event.value as u64);
+                }
+            }
+            // a transaction without a commit record is aborted
+            if transaction.success {
+                buf.push(b'C');
+                long2bytes(&mut buf, i_txn);
+            }
+        }
+        File::create(dir.join(format!("T{}.log", i_node + 1)))?.write_all(&buf)?;
+    }
+
+    Ok(())
+}
+
+// rw-register `:ok` and, for aborted transactions, `:fail` operations, one
+// per line; reads of the initial value are `nil`
+pub fn to_edn<W: Write>(histories: &[Session], mut writer: W) -> io::Result<()> {
+    let mut index = 0;
+    for (i_node, session) in histories.iter().enumerate() {
+        for transaction in session.iter() {
+            let mops: Vec<String> = transaction
+                .events
+                .iter()
+                .filter(|event| event.success)
+                .map(|event| match (event.write, event.value) {
+                    (true, value) => format!("[:w {} {}]", event.variable, value),
+                    (false, 0) => format!("[:r {} nil]", event.variable),
+                    (false, value) => format!("[:r {} {}]", event.variable, value),
+                })
+                .collect();
+            writeln!(
+                writer,
+                "{{:type {}, :f :txn, :value [{}], :process {}, :index {}}}",
+                if transaction.success { ":ok" } else { ":fail" },
+                mops.join(" "),
+                i_node,
+                index
+            )?;
+            index += 1;
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,18 @@
 use std::fmt;
-
-use hashbrown::HashMap;
-
-use rand::distributions::{Distribution, Uniform};
-use rand::Rng;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
 
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use chrono::{DateTime, Duration, Local};
 
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use db::workload::Workload;
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Clone)]
 pub struct Event {
     pub write: bool,
@@ -76,6 +80,11 @@ pub struct HistParams {
     n_variable: usize,
     n_transaction: usize,
     n_event: usize,
+    #[serde(default)]
+    workload: Workload,
+    // regenerates the history with `generate_history`
+    #[serde(default)]
+    seed: u64,
 }
 
 impl HistParams {
@@ -97,6 +106,12 @@ impl HistParams {
     pub fn get_event(&self) -> usize {
         self.n_event
     }
+    pub fn get_workload(&self) -> &Workload {
+        &self.workload
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -129,6 +144,14 @@ impl History {
         self.params.get_id()
     }
 
+    pub fn get_info(&self) -> &str {
+        &self.info
+    }
+
+    pub fn set_info(&mut self, info: String) {
+        self.info = info;
+    }
+
     pub fn get_data(&self) -> &Vec<Session> {
         &self.data
     }
@@ -150,45 +173,218 @@ impl History {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Bincode,
+    Json,
+    Yaml,
+}
+
+impl HistoryFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bincode" | "bin" => Some(HistoryFormat::Bincode),
+            "json" => Some(HistoryFormat::Json),
+            "yaml" | "yml" => Some(HistoryFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            HistoryFormat::Bincode => "bincode",
+            HistoryFormat::Json => "json",
+            HistoryFormat::Yaml => "yaml",
+        }
+    }
+}
+
+// layout of the histories saved before workloads and seeds; bincode has no
+// field names, so `#[serde(default)]` can't fill the missing fields in
+#[derive(Deserialize)]
+struct LegacyHistParams {
+    id: usize,
+    n_node: usize,
+    n_variable: usize,
+    n_transaction: usize,
+    n_event: usize,
+}
+
+#[derive(Deserialize)]
+struct LegacyHistory {
+    params: LegacyHistParams,
+    info: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    data: Vec<Session>,
+}
+
+impl From<LegacyHistory> for History {
+    fn from(legacy: LegacyHistory) -> Self {
+        let params = HistParams {
+            id: legacy.params.id,
+            n_node: legacy.params.n_node,
+            n_variable: legacy.params.n_variable,
+            n_transaction: legacy.params.n_transaction,
+            n_event: legacy.params.n_event,
+            ..Default::default()
+        };
+        History::new(params, legacy.info, legacy.start, legacy.end, legacy.data)
+    }
+}
+
+// a layout only matches if it takes up every byte
+fn from_bincode<T>(mut bytes: &[u8]) -> bincode::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let value = bincode::deserialize_from(&mut bytes)?;
+    if bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(Box::new(bincode::ErrorKind::Custom(format!(
+            "{} trailing bytes",
+            bytes.len()
+        ))))
+    }
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl History {
+    // format is picked by extension, bincode otherwise
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::load_as(
+            path,
+            HistoryFormat::from_path(path).unwrap_or(HistoryFormat::Bincode),
+        )
+    }
+
+    pub fn load_as(path: &Path, format: HistoryFormat) -> io::Result<Self> {
+        let mut buf_reader = BufReader::new(File::open(path)?);
+        match format {
+            HistoryFormat::Bincode => {
+                let mut content = Vec::new();
+                buf_reader.read_to_end(&mut content)?;
+                from_bincode(&content)
+                    .or_else(|e| {
+                        from_bincode::<LegacyHistory>(&content)
+                            .map(Self::from)
+                            .map_err(|_| e)
+                    })
+                    .map_err(invalid_data)
+            }
+            HistoryFormat::Json | HistoryFormat::Yaml => {
+                let mut content = String::new();
+                buf_reader.read_to_string(&mut content)?;
+                let hist = if format == HistoryFormat::Json {
+                    serde_json::from_str(&content).map_err(invalid_data)
+                } else {
+                    serde_yaml::from_str(&content).map_err(invalid_data)
+                };
+                // hand written histories may only contain the sessions
+                hist.or_else(|e| {
+                    let data: Result<Vec<Session>, _> = if format == HistoryFormat::Json {
+                        serde_json::from_str(&content).map_err(invalid_data)
+                    } else {
+                        serde_yaml::from_str(&content).map_err(invalid_data)
+                    };
+                    data.map(Self::from_data).map_err(|_| e)
+                })
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_as(
+            path,
+            HistoryFormat::from_path(path).unwrap_or(HistoryFormat::Bincode),
+        )
+    }
+
+    pub fn save_as(&self, path: &Path, format: HistoryFormat) -> io::Result<()> {
+        let buf_writer = BufWriter::new(File::create(path)?);
+        match format {
+            HistoryFormat::Bincode => {
+                bincode::serialize_into(buf_writer, self).map_err(invalid_data)
+            }
+            HistoryFormat::Json => {
+                serde_json::to_writer_pretty(buf_writer, self).map_err(invalid_data)
+            }
+            HistoryFormat::Yaml => serde_yaml::to_writer(buf_writer, self).map_err(invalid_data),
+        }
+    }
+
+    pub fn from_data(data: Vec<Session>) -> Self {
+        let now = Local::now();
+        History::new(
+            HistParams {
+                n_node: data.len(),
+                ..Default::default()
+            },
+            "imported".to_string(),
+            now,
+            now,
+            data,
+        )
+    }
+}
+
+// `history.bincode`, `history.json` or `history.yaml` in an executed history directory
+pub fn find_history_file(dir: &Path) -> Option<PathBuf> {
+    [
+        HistoryFormat::Bincode,
+        HistoryFormat::Json,
+        HistoryFormat::Yaml,
+    ]
+    .iter()
+    .map(|format| dir.join("history").with_extension(format.extension()))
+    .find(|path| path.is_file())
+}
+
 pub fn generate_single_history(
     n_node: usize,
     n_variable: usize,
     n_transaction: usize,
     n_event: usize,
+    seed: u64,
 ) -> Vec<Session> {
-    let mut counters = HashMap::new();
-    let mut random_generator = rand::thread_rng();
-    let read_variable_range = Uniform::from(0..n_variable);
-    let jump = (n_variable as f64 / n_node as f64).ceil() as usize;
-    (0..n_node)
-        .map(|i_node| {
-            let i = i_node * jump;
-            let j = std::cmp::min((i_node + 1) * jump, n_variable);
-            // let write_variable_range = Uniform::from(i..j);
-            (0..n_transaction)
-                .map(|_| Transaction {
-                    events: (0..n_event)
-                        .map(|_| {
-                            if random_generator.gen() {
-                                let variable = read_variable_range.sample(&mut random_generator);
-                                Event::read(variable)
-                            } else {
-                                let variable = read_variable_range.sample(&mut random_generator);
-                                // let variable = write_variable_range.sample(&mut random_generator);
-                                let value = {
-                                    let entry = counters.entry(variable).or_insert(0);
-                                    *entry += 1;
-                                    *entry
-                                };
-                                Event::write(variable, value)
-                            }
-                        })
-                        .collect(),
-                    success: false,
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>()
+    Workload::fixed(n_event).generate(
+        n_node,
+        n_variable,
+        n_transaction,
+        &mut StdRng::seed_from_u64(seed),
+    )
+}
+
+// the sessions generated for `params`, as when they were first generated
+pub fn generate_history(params: &HistParams) -> Vec<Session> {
+    params.workload.generate(
+        params.n_node,
+        params.n_variable,
+        params.n_transaction,
+        &mut StdRng::seed_from_u64(params.seed),
+    )
+}
+
+// seed of the `i_hist`-th history by splitmix64, so that a history depends
+// neither on the others nor on the thread generating it
+pub fn history_seed(seed: u64, i_hist: usize) -> u64 {
+    let mut z = seed.wrapping_add((i_hist as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 pub fn generate_mult_histories(
@@ -196,22 +392,36 @@ pub fn generate_mult_histories(
     n_node: usize,
     n_variable: usize,
     n_transaction: usize,
-    n_event: usize,
+    workload: &Workload,
+    seed: u64,
 ) -> Vec<History> {
     (0..n_hist)
         .into_par_iter()
         .map(|i_hist| {
+            let mut params = HistParams {
+                id: i_hist,
+                n_node,
+                n_variable,
+                n_transaction,
+                n_event: workload.max_event,
+                workload: workload.clone(),
+                seed: history_seed(seed, i_hist),
+            };
             let start_time = Local::now();
-            let hist = generate_single_history(n_node, n_variable, n_transaction, n_event);
+            let hist = generate_history(&params);
             let end_time = Local::now();
+            if workload.application.is_some() {
+                // the rows the application uses, for clusters to create
+                params.n_variable = hist
+                    .iter()
+                    .flat_map(|session| session.iter())
+                    .flat_map(|transaction| transaction.events.iter())
+                    .map(|event| event.variable + 1)
+                    .max()
+                    .unwrap_or(0);
+            }
             History {
-                params: HistParams {
-                    id: i_hist,
-                    n_node,
-                    n_variable,
-                    n_transaction,
-                    n_event,
-                },
+                params,
                 info: "generated".to_string(),
                 start: start_time,
                 end: end_time,
@@ -0,0 +1,468 @@
+use hashbrown::{HashMap, HashSet};
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use db::history::{Event, History, Session, Transaction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    // PolySI text, one `r(key,value,session,txn)` or `w(...)` per line
+    Text,
+    // directory of Cobra binary `.log` files, one per client
+    Cobra,
+    // Elle/Jepsen EDN, rw-register transactions
+    Elle,
+}
+
+impl ImportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(ImportFormat::Text),
+            "cobra" => Some(ImportFormat::Cobra),
+            "elle" | "edn" => Some(ImportFormat::Elle),
+            _ => None,
+        }
+    }
+}
+
+pub fn import(path: &Path, format: ImportFormat) -> io::Result<History> {
+    let (data, info) = match format {
+        ImportFormat::Text => (from_text(BufReader::new(File::open(path)?))?, "polysi text"),
+        ImportFormat::Cobra => (from_cobra_logs(path)?, "cobra logs"),
+        ImportFormat::Elle => {
+            let mut content = String::new();
+            File::open(path)?.read_to_string(&mut content)?;
+            (from_edn(&content)?, "elle edn")
+        }
+    };
+    let mut hist = History::from_data(data);
+    hist.set_info(info.to_string());
+    Ok(hist)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// maps sparse ids to dense indices, in order of appearance
+#[derive(Default)]
+struct IdMap {
+    ids: HashMap<u64, usize>,
+}
+
+impl IdMap {
+    fn get(&mut self, id: u64) -> (usize, bool) {
+        let next = self.ids.len();
+        let index = *self.ids.entry(id).or_insert(next);
+        (index, index == next)
+    }
+}
+
+fn committed(write: bool, variable: usize, value: usize) -> Event {
+    Event {
+        write,
+        variable,
+        value,
+        success: true,
+    }
+}
+
+// value 0 is the initial value, as in dbcop
+pub fn from_text<R: BufRead>(reader: R) -> io::Result<Vec<Session>> {
+    let mut sessions: Vec<Session> = Vec::new();
+    let mut session_ids = IdMap::default();
+    let mut transaction_pos: HashMap<u64, (usize, usize)> = HashMap::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let write = line.starts_with("w(");
+        let args: Vec<u64> = if (write || line.starts_with("r(")) && line.ends_with(')') {
+            line[2..line.len() - 1]
+                .split(',')
+                .map(|arg| arg.trim().parse())
+                .collect::<Result<_, _>>()
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        if args.len() != 4 {
+            return Err(invalid_data(format!("invalid line {}", line)));
+        }
+
+        let (i_session, new_session) = session_ids.get(args[2]);
+        if new_session {
+            sessions.push(Session::new());
+        }
+
+        let &mut (t_session, t_index) = transaction_pos.entry(args[3]).or_insert_with(|| {
+            sessions[i_session].push(Transaction {
+                events: Vec::new(),
+                success: true,
+            });
+            (i_session, sessions[i_session].len() - 1)
+        });
+
+        sessions[t_session][t_index].events.push(committed(
+            write,
+            args[0] as usize,
+            args[1] as usize,
+        ));
+    }
+
+    Ok(sessions)
+}
+
+pub const COBRA_INIT_WRITE_ID: u64 = 0xbebe_ebee;
+pub const COBRA_INIT_TXN_ID: u64 = 0xbebe_ebee;
+pub const COBRA_NULL_TXN_ID: u64 = 0xdead_beef;
+
+pub fn from_cobra_logs(dir: &Path) -> io::Result<Vec<Session>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry_res| match entry_res {
+            Ok(ref entry) if entry.path().extension().map_or(false, |ext| ext == "log") => {
+                Some(entry.path())
+            }
+            _ => None,
+        })
+        .collect();
+
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let mut buf = Vec::new();
+            File::open(path)?.read_to_end(&mut buf)?;
+            parse_cobra_log(&buf)
+        })
+        .collect()
+}
+
+fn bytes2long(buf: &[u8], i: &mut usize) -> io::Result<u64> {
+    if *i + 8 > buf.len() {
+        return Err(invalid_data("truncated cobra log".to_string()));
+    }
+    let mut value: u64 = 0;
+    for byte in buf[*i..*i + 8].iter() {
+        value = (value << 8) + u64::from(*byte);
+    }
+    *i += 8;
+    Ok(value)
+}
+
+// (startTx, txnId) : 9B
+// (commitTx, txnId) : 9B
+// (write, writeId, key, val) : 25B
+// (read, write_TxnId, writeId, key, value) : 33B
+//
+// write ids are unique, so they are used as values
+pub fn parse_cobra_log(buf: &[u8]) -> io::Result<Session> {
+    let mut sess = Session::new();
+    let mut cur_txn: Option<Transaction> = None;
+    let mut i = 0;
+
+    while i < buf.len() {
+        let op_type = buf[i] as char;
+        i += 1;
+
+        match op_type {
+            'S' => {
+                bytes2long(buf, &mut i)?;
+                // a started but never committed transaction is aborted
+                if let Some(txn) = cur_txn.take() {
+                    sess.push(txn);
+                }
+                cur_txn = Some(Transaction {
+                    events: Vec::new(),
+                    success: false,
+                });
+            }
+            'C' => {
+                bytes2long(buf, &mut i)?;
+                let mut txn = cur_txn
+                    .take()
+                    .ok_or_else(|| invalid_data("commit without start".to_string()))?;
+                txn.success = true;
+                sess.push(txn);
+            }
+            'W' => {
+                let wid = bytes2long(buf, &mut i)?;
+                let key = bytes2long(buf, &mut i)?;
+                bytes2long(buf, &mut i)?;
+                cur_txn
+                    .as_mut()
+                    .ok_or_else(|| invalid_data("write outside transaction".to_string()))?
+                    .events
+                    .push(committed(true, key as usize, wid as usize));
+            }
+            'R' => {
+                let w_txnid = bytes2long(buf, &mut i)?;
+                let w_wid = bytes2long(buf, &mut i)?;
+                let key = bytes2long(buf, &mut i)?;
+                bytes2long(buf, &mut i)?;
+                let txn = cur_txn
+                    .as_mut()
+                    .ok_or_else(|| invalid_data("read outside transaction".to_string()))?;
+                if w_txnid == COBRA_INIT_TXN_ID || w_txnid == COBRA_NULL_TXN_ID {
+                    if w_wid == COBRA_INIT_WRITE_ID || w_wid == COBRA_NULL_TXN_ID {
+                        txn.events.push(committed(false, key as usize, 0));
+                    }
+                    // otherwise a garbage collection read, which is not part of the history
+                } else {
+                    txn.events
+                        .push(committed(false, key as usize, w_wid as usize));
+                }
+            }
+            _ => return Err(invalid_data(format!("unknown cobra op {:?}", op_type))),
+        }
+    }
+
+    if let Some(txn) = cur_txn {
+        sess.push(txn);
+    }
+
+    Ok(sess)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Edn {
+    Nil,
+    Int(i64),
+    Keyword(String),
+    Symbol(String),
+    Str(String),
+    Vector(Vec<Edn>),
+    Map(Vec<(Edn, Edn)>),
+}
+
+struct EdnParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> EdnParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == ';' {
+                while self.chars.peek().map_or(false, |&c| c != '\n') {
+                    self.chars.next();
+                }
+            } else if c.is_whitespace() || c == ',' {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_seq(&mut self, close: char) -> io::Result<Vec<Edn>> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(&c) if c == close => {
+                    self.chars.next();
+                    return Ok(items);
+                }
+                Some(_) => items.push(self.parse()?),
+                None => return Err(invalid_data(format!("missing {:?}", close))),
+            }
+        }
+    }
+
+    fn parse(&mut self) -> io::Result<Edn> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some('[') => Ok(Edn::Vector(self.parse_seq(']')?)),
+            Some('(') => Ok(Edn::Vector(self.parse_seq(')')?)),
+            Some('{') => {
+                let mut items = self.parse_seq('}')?.into_iter();
+                let mut pairs = Vec::new();
+                while let Some(k) = items.next() {
+                    let v = items
+                        .next()
+                        .ok_or_else(|| invalid_data("odd number of map forms".to_string()))?;
+                    pairs.push((k, v));
+                }
+                Ok(Edn::Map(pairs))
+            }
+            Some('#') => {
+                // sets are read as vectors, tagged literals as their value
+                if self.chars.peek() == Some(&'{') {
+                    self.chars.next();
+                    Ok(Edn::Vector(self.parse_seq('}')?))
+                } else {
+                    self.parse_token();
+                    self.parse()
+                }
+            }
+            Some('"') => {
+                let mut s = String::new();
+                while let Some(c) = self.chars.next() {
+                    match c {
+                        '"' => return Ok(Edn::Str(s)),
+                        '\\' => s.extend(self.chars.next()),
+                        _ => s.push(c),
+                    }
+                }
+                Err(invalid_data("unterminated string".to_string()))
+            }
+            Some(c) => {
+                let token = format!("{}{}", c, self.parse_token());
+                Ok(if token == "nil" {
+                    Edn::Nil
+                } else if token.starts_with(':') {
+                    Edn::Keyword(token[1..].to_string())
+                } else if let Ok(n) = token.parse() {
+                    Edn::Int(n)
+                } else {
+                    Edn::Symbol(token)
+                })
+            }
+            None => Err(invalid_data("unexpected end of edn".to_string())),
+        }
+    }
+
+    fn parse_token(&mut self) -> String {
+        let mut token = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || ",()[]{}\"".contains(c) {
+                break;
+            }
+            token.push(c);
+            self.chars.next();
+        }
+        token
+    }
+}
+
+impl Edn {
+    fn get(&self, key: &str) -> Option<&Edn> {
+        match self {
+            Edn::Map(pairs) => pairs
+                .iter()
+                .find(|(k, _)| k == &Edn::Keyword(key.to_string()))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_usize(&self) -> io::Result<usize> {
+        match self {
+            Edn::Int(n) if *n >= 0 => Ok(*n as usize),
+            Edn::Nil => Ok(0),
+            _ => Err(invalid_data(format!("expected a value, found {:?}", self))),
+        }
+    }
+}
+
+// completed `:txn` operations in order: `:ok` ones committed, `:fail` ones
+// aborted and `:info` ones committed only if one of their writes is read. the
+// reads of the last two are unknown and left out. list-append histories are
+// rejected, as the order of the appends their reads observe has no
+// counterpart in a history of registers
+pub fn from_edn(content: &str) -> io::Result<Vec<Session>> {
+    let mut parser = EdnParser {
+        chars: content.chars().peekable(),
+    };
+
+    let mut ops = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.chars.peek().is_none() {
+            break;
+        }
+        match parser.parse()? {
+            // a whole history as one vector
+            Edn::Vector(items) => ops.extend(items),
+            op => ops.push(op),
+        }
+    }
+
+    // (process, status, events) of every completion
+    let mut completed = Vec::new();
+
+    for op in ops.iter() {
+        if op.get("f") != Some(&Edn::Keyword("txn".to_string())) {
+            continue;
+        }
+
+        let status = match op.get("type") {
+            Some(Edn::Keyword(ref status)) if status == "ok" => Some(true),
+            Some(Edn::Keyword(ref status)) if status == "fail" => Some(false),
+            Some(Edn::Keyword(ref status)) if status == "info" => None,
+            // invocations
+            _ => continue,
+        };
+
+        let process = match op.get("process") {
+            Some(Edn::Int(n)) => *n as u64,
+            _ => return Err(invalid_data(format!("missing :process in {:?}", op))),
+        };
+
+        let mops = match op.get("value") {
+            Some(Edn::Vector(mops)) => mops,
+            _ => return Err(invalid_data(format!("missing :value in {:?}", op))),
+        };
+
+        let mut events = Vec::new();
+        for mop in mops.iter() {
+            match mop {
+                Edn::Vector(ref items) if items.len() == 3 => {
+                    let write = match items[0] {
+                        Edn::Keyword(ref f) if f == "r" => false,
+                        Edn::Keyword(ref f) if f == "w" => true,
+                        Edn::Keyword(ref f) if f == "append" => {
+                            return Err(invalid_data(
+                                "list-append histories are not supported".to_string(),
+                            ))
+                        }
+                        _ => return Err(invalid_data(format!("unknown micro-op {:?}", mop))),
+                    };
+                    if write || status == Some(true) {
+                        events.push(committed(write, items[1].as_usize()?, items[2].as_usize()?));
+                    }
+                }
+                _ => return Err(invalid_data(format!("unknown micro-op {:?}", mop))),
+            }
+        }
+
+        completed.push((process, status, events));
+    }
+
+    let read: HashSet<(usize, usize)> = completed
+        .iter()
+        .filter(|(_, status, _)| *status == Some(true))
+        .flat_map(|(_, _, events)| events.iter())
+        .filter(|event| !event.write)
+        .map(|event| (event.variable, event.value))
+        .collect();
+
+    let mut sessions: Vec<Session> = Vec::new();
+    let mut session_ids = IdMap::default();
+
+    for (process, status, events) in completed {
+        let success = status.unwrap_or_else(|| {
+            events
+                .iter()
+                .any(|event| read.contains(&(event.variable, event.value)))
+        });
+
+        let (i_session, new_session) = session_ids.get(process);
+        if new_session {
+            sessions.push(Session::new());
+        }
+        sessions[i_session].push(Transaction { events, success });
+    }
+
+    Ok(sessions)
+}
@@ -0,0 +1,359 @@
+use db::cluster::{Cluster, ClusterNode, Node};
+use db::history::{history_seed, HistParams, Session, Transaction};
+
+use hashbrown::HashMap;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Variable = usize;
+// lamport clock and origin replica of a commit; the larger one wins a variable
+type Stamp = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Isolation {
+    // optimistic: aborts if a read or written variable changed since the start
+    Serializable,
+    // reads a snapshot, first committer of a variable wins
+    SnapshotIsolation,
+    // snapshot isolation per replica, commits reach the others in causal order
+    ParallelSnapshotIsolation,
+    // like the above without the write conflict check; the last stamp wins
+    Causal,
+    // reads the latest commit of a single replica
+    ReadCommitted,
+    // buggy snapshot isolation without the write conflict check
+    LostUpdate,
+    // buggy causal, delivers remote commits ignoring their dependencies
+    UnorderedDelivery,
+}
+
+impl Isolation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ser" => Some(Isolation::Serializable),
+            "si" => Some(Isolation::SnapshotIsolation),
+            "psi" => Some(Isolation::ParallelSnapshotIsolation),
+            "cc" => Some(Isolation::Causal),
+            "rc" => Some(Isolation::ReadCommitted),
+            "lost_update" => Some(Isolation::LostUpdate),
+            "unordered" => Some(Isolation::UnorderedDelivery),
+            _ => None,
+        }
+    }
+
+    // every replica commits locally and propagates later
+    pub fn is_replicated(self) -> bool {
+        match self {
+            Isolation::ParallelSnapshotIsolation
+            | Isolation::Causal
+            | Isolation::UnorderedDelivery => true,
+            _ => false,
+        }
+    }
+
+    fn reads_snapshot(self) -> bool {
+        self != Isolation::ReadCommitted
+    }
+
+    fn checks_writes(self) -> bool {
+        match self {
+            Isolation::Serializable
+            | Isolation::SnapshotIsolation
+            | Isolation::ParallelSnapshotIsolation => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Commit {
+    // commits of every replica visible to this one
+    deps: Vec<usize>,
+    stamp: Stamp,
+    writes: HashMap<Variable, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Replica {
+    data: HashMap<Variable, (usize, Stamp)>,
+    // number of commits of every replica applied here
+    applied: Vec<usize>,
+    clock: usize,
+}
+
+impl Replica {
+    fn apply(&mut self, origin: usize, commit: &Commit) {
+        for (&x, &value) in commit.writes.iter() {
+            let entry = self.data.entry(x).or_insert((0, (0, 0)));
+            if entry.1 < commit.stamp {
+                *entry = (value, commit.stamp);
+            }
+        }
+        self.applied[origin] += 1;
+        self.clock = std::cmp::max(self.clock, commit.stamp.0);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Running {
+    replica: usize,
+    start: Vec<usize>,
+    snapshot: HashMap<Variable, (usize, Stamp)>,
+    reads: Vec<Variable>,
+    writes: HashMap<Variable, usize>,
+}
+
+// multi-version store of one or more replicas, stepped one operation at a
+// time so a caller decides the interleaving
+#[derive(Debug, Clone)]
+pub struct Database {
+    isolation: Isolation,
+    replicas: Vec<Replica>,
+    // commits of every replica, in order
+    log: Vec<Vec<Commit>>,
+    // replica and sequence number of every commit writing a variable
+    writers: HashMap<Variable, Vec<(usize, usize)>>,
+}
+
+impl Database {
+    pub fn new(isolation: Isolation, n_replica: usize) -> Self {
+        let n_replica = if isolation.is_replicated() {
+            n_replica
+        } else {
+            1
+        };
+        Database {
+            isolation,
+            replicas: vec![
+                Replica {
+                    data: HashMap::new(),
+                    applied: vec![0; n_replica],
+                    clock: 0,
+                };
+                n_replica
+            ],
+            log: vec![Vec::new(); n_replica],
+            writers: HashMap::new(),
+        }
+    }
+
+    pub fn get_isolation(&self) -> Isolation {
+        self.isolation
+    }
+
+    // the replica serving a node
+    pub fn replica_of(&self, node: usize) -> usize {
+        node % self.replicas.len()
+    }
+
+    pub fn begin(&self, replica: usize) -> Running {
+        let replica_state = &self.replicas[replica];
+        Running {
+            replica,
+            start: replica_state.applied.clone(),
+            snapshot: if self.isolation.reads_snapshot() {
+                replica_state.data.clone()
+            } else {
+                HashMap::new()
+            },
+            reads: Vec::new(),
+            writes: HashMap::new(),
+        }
+    }
+
+    pub fn read(&self, txn: &mut Running, x: Variable) -> usize {
+        if let Some(&value) = txn.writes.get(&x) {
+            return value;
+        }
+        txn.reads.push(x);
+        let visible = if self.isolation.reads_snapshot() {
+            &txn.snapshot
+        } else {
+            &self.replicas[txn.replica].data
+        };
+        visible.get(&x).map_or(0, |&(value, _)| value)
+    }
+
+    pub fn write(&self, txn: &mut Running, x: Variable, value: usize) {
+        txn.writes.insert(x, value);
+    }
+
+    // false if the transaction aborts
+    pub fn commit(&mut self, txn: Running) -> bool {
+        let concurrent = |x: &Variable| {
+            self.writers.get(x).map_or(false, |writers| {
+                writers
+                    .iter()
+                    .any(|&(origin, seq)| seq >= txn.start[origin])
+            })
+        };
+        if self.isolation.checks_writes() && txn.writes.keys().any(&concurrent) {
+            return false;
+        }
+        if self.isolation == Isolation::Serializable && txn.reads.iter().any(&concurrent) {
+            return false;
+        }
+
+        let replica = txn.replica;
+        let seq = self.log[replica].len();
+        let commit = Commit {
+            deps: self.replicas[replica].applied.clone(),
+            stamp: (self.replicas[replica].clock + 1, replica),
+            writes: txn.writes,
+        };
+        for &x in commit.writes.keys() {
+            self.writers
+                .entry(x)
+                .or_insert_with(Vec::new)
+                .push((replica, seq));
+        }
+        self.replicas[replica].apply(replica, &commit);
+        self.log[replica].push(commit);
+        true
+    }
+
+    // applies a random pending commit of another replica, if any is ready
+    pub fn deliver<R: Rng>(&mut self, replica: usize, rng: &mut R) -> bool {
+        let ordered = self.isolation != Isolation::UnorderedDelivery;
+        let ready: Vec<usize> = {
+            let applied = &self.replicas[replica].applied;
+            (0..self.log.len())
+                .filter(|&origin| match self.log[origin].get(applied[origin]) {
+                    Some(commit) => {
+                        !ordered
+                            || commit
+                                .deps
+                                .iter()
+                                .zip(applied.iter())
+                                .all(|(dep, seen)| dep <= seen)
+                    }
+                    None => false,
+                })
+                .collect()
+        };
+        if ready.is_empty() {
+            return false;
+        }
+        let origin = ready[Uniform::from(0..ready.len()).sample(rng)];
+        let commit = self.log[origin][self.replicas[replica].applied[origin]].clone();
+        self.replicas[replica].apply(origin, &commit);
+        true
+    }
+
+    // runs the next step of the session's transaction `i_transaction`:
+    // begins it, executes the event `i_event`, or commits it once all ran
+    pub fn step(
+        &mut self,
+        replica: usize,
+        running: &mut Option<Running>,
+        session: &mut Session,
+        i_transaction: usize,
+        i_event: usize,
+    ) {
+        let transaction = &mut session[i_transaction];
+        let mut txn = running.take().unwrap_or_else(|| self.begin(replica));
+        if i_event < transaction.events.len() {
+            let event = &mut transaction.events[i_event];
+            if event.write {
+                self.write(&mut txn, event.variable, event.value);
+            } else {
+                event.value = self.read(&mut txn, event.variable);
+            }
+            event.success = true;
+            *running = Some(txn);
+        } else {
+            transaction.success = self.commit(txn);
+        }
+    }
+}
+
+// a node of the in-memory database, executing on the replica it is served by
+#[derive(Debug, Clone)]
+pub struct MemoryNode {
+    db: Arc<Mutex<Database>>,
+    replica: usize,
+    // of the deliveries; the interleaving of the sessions is up to the threads
+    seed: u64,
+}
+
+impl ClusterNode for MemoryNode {
+    fn exec_session(&self, hist: &mut Session) {
+        self.exec_session_streaming(hist, &mut |_, _| {});
+    }
+
+    fn exec_session_streaming(
+        &self,
+        hist: &mut Session,
+        done: &mut dyn FnMut(usize, &Transaction),
+    ) {
+        let mut random_generator = StdRng::seed_from_u64(self.seed);
+        for i_transaction in 0..hist.len() {
+            let mut running = None;
+            for i_event in 0..=hist[i_transaction].events.len() {
+                {
+                    let mut db = self.db.lock().unwrap();
+                    if random_generator.gen() {
+                        db.deliver(self.replica, &mut random_generator);
+                    }
+                    db.step(self.replica, &mut running, hist, i_transaction, i_event);
+                }
+                // lets other sessions interleave
+                thread::yield_now();
+            }
+            done(i_transaction, &hist[i_transaction]);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryCluster {
+    isolation: Isolation,
+    nodes: Vec<Node>,
+    db: Arc<Mutex<Database>>,
+    // of the history being executed
+    seed: u64,
+}
+
+impl MemoryCluster {
+    pub fn new(isolation: Isolation, n_node: usize) -> Self {
+        MemoryCluster {
+            isolation,
+            nodes: MemoryCluster::node_vec(&vec!["127.0.0.1"; n_node]),
+            db: Arc::new(Mutex::new(Database::new(isolation, n_node))),
+            seed: 0,
+        }
+    }
+}
+
+impl Cluster<MemoryNode> for MemoryCluster {
+    fn n_node(&self) -> usize {
+        self.nodes.len()
+    }
+    fn setup(&self) -> bool {
+        true
+    }
+    fn get_node(&self, id: usize) -> Node {
+        self.nodes[id].clone()
+    }
+    fn get_cluster_node(&self, id: usize) -> MemoryNode {
+        MemoryNode {
+            db: self.db.clone(),
+            replica: self.db.lock().unwrap().replica_of(id),
+            seed: history_seed(self.seed, id),
+        }
+    }
+    fn setup_test(&mut self, p: &HistParams) {
+        // a fresh database for every history
+        self.db = Arc::new(Mutex::new(Database::new(self.isolation, self.nodes.len())));
+        self.seed = p.get_seed();
+    }
+    fn cleanup(&self) {}
+    fn info(&self) -> String {
+        format!("in-memory {:?}", self.isolation)
+    }
+}
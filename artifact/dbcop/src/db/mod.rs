@@ -1,2 +1,6 @@
 pub mod cluster;
+pub mod export;
 pub mod history;
+pub mod import;
+pub mod memory;
+pub mod workload;
@@ -0,0 +1,484 @@
+use std::ops::Range;
+
+pub mod rubis;
+pub mod tpcc;
+pub mod twitter;
+
+use hashbrown::HashMap;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use consistency::Consistency;
+use db::history::{Event, Session, Transaction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyDistribution {
+    Uniform,
+    // the i-th variable is picked with weight 1 / (i + 1)^theta
+    Zipf(f64),
+    // `fraction` of the accesses go to the first `hot` variables
+    Hotspot { hot: usize, fraction: f64 },
+}
+
+impl KeyDistribution {
+    fn weight(&self, n_variable: usize, variable: usize) -> f64 {
+        match *self {
+            KeyDistribution::Uniform => 1.0,
+            KeyDistribution::Zipf(theta) => 1.0 / ((variable + 1) as f64).powf(theta),
+            KeyDistribution::Hotspot { hot, fraction } => {
+                let hot = std::cmp::min(hot, n_variable);
+                if variable < hot {
+                    fraction / hot as f64
+                } else {
+                    (1.0 - fraction) / (n_variable - hot) as f64
+                }
+            }
+        }
+    }
+}
+
+// transaction patterns made to provoke one anomaly, forbidden from the level
+// given by `stresses` on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    // two sessions each read a pair of variables and write one of them
+    WriteSkew,
+    // two sessions write one variable each, the others read both in
+    // either order
+    LongFork,
+    // every transaction reads and writes one of two hot variables
+    LostUpdate,
+    // every session reads the variables of the two sessions before it and
+    // writes its own
+    CausalityChain,
+    // half the sessions write a group of variables at once, the other
+    // half read the whole group
+    FracturedRead,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "write_skew" => Some(Preset::WriteSkew),
+            "long_fork" => Some(Preset::LongFork),
+            "lost_update" => Some(Preset::LostUpdate),
+            "causality_chain" => Some(Preset::CausalityChain),
+            "fractured_read" => Some(Preset::FracturedRead),
+            _ => None,
+        }
+    }
+
+    // the weakest level that forbids the anomaly
+    pub fn stresses(self) -> Consistency {
+        match self {
+            Preset::WriteSkew => Consistency::Serializable,
+            Preset::LongFork => Consistency::Prefix,
+            Preset::LostUpdate => Consistency::SnapshotIsolation,
+            Preset::CausalityChain => Consistency::Causal,
+            Preset::FracturedRead => Consistency::ReadAtomic,
+        }
+    }
+
+    // the fewest sessions and variables that make up the anomaly
+    pub fn needs(self) -> (usize, usize) {
+        match self {
+            Preset::WriteSkew => (2, 2),
+            Preset::LongFork => (4, 2),
+            Preset::LostUpdate => (2, 1),
+            Preset::CausalityChain => (3, 3),
+            Preset::FracturedRead => (2, 2),
+        }
+    }
+
+    fn generate<R: Rng>(
+        self,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        rng: &mut R,
+    ) -> Vec<Session> {
+        let mut counters = HashMap::new();
+        let mut write = |variable: usize| {
+            let entry = counters.entry(variable).or_insert(0);
+            *entry += 1;
+            Event::write(variable, *entry)
+        };
+        let hot_variables = Uniform::from(0..std::cmp::min(2, n_variable));
+        let pairs = Uniform::from(0..std::cmp::max(n_variable / 2, 1));
+        let group = std::cmp::min(3, n_variable);
+        let groups = Uniform::from(0..n_variable / group);
+
+        let mut sessions: Vec<Session> = vec![Vec::new(); n_node];
+
+        for _ in 0..n_transaction {
+            // the variables the sessions work on in this round
+            let x = 2 * pairs.sample(rng) % n_variable;
+            let y = (x + 1) % n_variable;
+            let start = groups.sample(rng) * group;
+            let hot = hot_variables.sample(rng);
+
+            for (i_node, session) in sessions.iter_mut().enumerate() {
+                let events = match self {
+                    Preset::WriteSkew => {
+                        let own = if i_node % 2 == 0 { x } else { y };
+                        vec![Event::read(x), Event::read(y), write(own)]
+                    }
+                    Preset::LongFork => match i_node % 4 {
+                        0 => vec![write(x)],
+                        1 => vec![write(y)],
+                        2 => vec![Event::read(x), Event::read(y)],
+                        _ => vec![Event::read(y), Event::read(x)],
+                    },
+                    Preset::LostUpdate => vec![Event::read(hot), write(hot)],
+                    Preset::CausalityChain => {
+                        let own = i_node % n_variable;
+                        vec![
+                            Event::read((i_node + n_node - 1) % n_node % n_variable),
+                            Event::read((i_node + n_node - 2) % n_node % n_variable),
+                            write(own),
+                        ]
+                    }
+                    Preset::FracturedRead => {
+                        if i_node % 2 == 0 {
+                            (start..start + group).map(&mut write).collect()
+                        } else {
+                            (start..start + group).map(Event::read).collect()
+                        }
+                    }
+                };
+                session.push(Transaction {
+                    events,
+                    success: false,
+                });
+            }
+        }
+
+        sessions
+    }
+}
+
+// key-value versions of the applications Cobra and PolySI are evaluated on;
+// the sessions are clients, the variables table rows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Application {
+    Tpcc { warehouses: usize },
+    Twitter { users: usize },
+    Rubis { users: usize },
+}
+
+impl Application {
+    // a warehouse, or a hundred users, by default
+    pub fn from_name(name: &str, scale: Option<usize>) -> Option<Self> {
+        match name {
+            "tpcc" => Some(Application::Tpcc {
+                warehouses: scale.unwrap_or(1),
+            }),
+            "twitter" => Some(Application::Twitter {
+                users: scale.unwrap_or(100),
+            }),
+            "rubis" => Some(Application::Rubis {
+                users: scale.unwrap_or(100),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn generate<R: Rng>(
+        self,
+        n_node: usize,
+        n_transaction: usize,
+        rng: &mut R,
+    ) -> Vec<Session> {
+        match self {
+            Application::Tpcc { warehouses } => {
+                tpcc::generate(n_node, warehouses, n_transaction, rng)
+            }
+            Application::Twitter { users } => twitter::generate(n_node, users, n_transaction, rng),
+            Application::Rubis { users } => rubis::generate(n_node, users, n_transaction, rng),
+        }
+    }
+}
+
+// a row of an application table: table, then up to two indices
+type Row = (usize, usize, usize);
+
+// variables of the rows used by an application, numbered as they come, with
+// values unique per variable to write to them
+#[derive(Default)]
+struct KeySpace {
+    ids: HashMap<Row, usize>,
+    counters: HashMap<usize, usize>,
+}
+
+impl KeySpace {
+    fn key(&mut self, row: Row) -> usize {
+        let n_key = self.ids.len();
+        *self.ids.entry(row).or_insert(n_key)
+    }
+
+    fn read(&mut self, row: Row) -> Event {
+        Event::read(self.key(row))
+    }
+
+    fn write(&mut self, row: Row) -> Event {
+        let variable = self.key(row);
+        let entry = self.counters.entry(variable).or_insert(0);
+        *entry += 1;
+        Event::write(variable, *entry)
+    }
+}
+
+// index of the picked weight
+fn pick<R: Rng>(weights: &[usize], rng: &mut R) -> usize {
+    let mut point = Uniform::from(0..weights.iter().sum::<usize>()).sample(rng);
+    weights
+        .iter()
+        .position(|&weight| {
+            if point < weight {
+                true
+            } else {
+                point -= weight;
+                false
+            }
+        })
+        .unwrap()
+}
+
+// shape of the generated transactions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Workload {
+    pub keys: KeyDistribution,
+    // fractions of read-only and write-only transactions
+    pub read_only: f64,
+    pub write_only: f64,
+    // chance of a read among the events of the other transactions
+    pub read_ratio: f64,
+    // events per transaction, picked uniformly
+    pub min_event: usize,
+    pub max_event: usize,
+    // chance of a write being read just before, in the same transaction
+    pub read_modify_write: f64,
+    // every session writes its own slice of the variables only
+    pub partition: bool,
+    // overrides the above
+    #[serde(default)]
+    pub preset: Option<Preset>,
+    // overrides the above, `n_variable` included
+    #[serde(default)]
+    pub application: Option<Application>,
+    // after every this many transactions of a session, one writing a
+    // variable of the session and reading those of the others, like Cobra's
+    // fences; 0 for none
+    #[serde(default)]
+    pub fence: usize,
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Workload::fixed(2)
+    }
+}
+
+// picks variables of a range, by the weights of a distribution
+struct KeyChooser {
+    range: Range<usize>,
+    cumulative: Vec<f64>,
+}
+
+impl KeyChooser {
+    fn new(keys: KeyDistribution, n_variable: usize, range: Range<usize>) -> Self {
+        let mut total = 0.0;
+        let cumulative = range
+            .clone()
+            .map(|variable| {
+                total += keys.weight(n_variable, variable);
+                total
+            })
+            .collect();
+        KeyChooser { range, cumulative }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        let total = *self.cumulative.last().unwrap();
+        let point = rng.gen::<f64>() * total;
+        let i = self.cumulative.partition_point(|&c| c <= point);
+        self.range.start + std::cmp::min(i, self.cumulative.len() - 1)
+    }
+}
+
+impl Workload {
+    // the original workload: `n_event` events, a fair coin for read or write,
+    // uniform variables
+    pub fn fixed(n_event: usize) -> Self {
+        Workload {
+            keys: KeyDistribution::Uniform,
+            read_only: 0.0,
+            write_only: 0.0,
+            read_ratio: 0.5,
+            min_event: n_event,
+            max_event: n_event,
+            read_modify_write: 0.0,
+            partition: false,
+            preset: None,
+            application: None,
+            fence: 0,
+        }
+    }
+
+    // why `generate` can't make a meaningful history of these dimensions, if
+    // it can't
+    pub fn check(&self, n_node: usize, n_variable: usize) -> Result<(), String> {
+        if self.application.is_some() {
+            return Ok(());
+        }
+        // fences take the last `n_node` variables
+        let n_own = if self.fence == 0 {
+            n_variable
+        } else {
+            std::cmp::max(n_variable, n_node + 1) - n_node
+        };
+        let (min_node, min_variable) = match self.preset {
+            Some(preset) => preset.needs(),
+            None => (1, 1),
+        };
+        if n_node < min_node {
+            Err(format!(
+                "the workload needs at least {} sessions, got {}",
+                min_node, n_node
+            ))
+        } else if n_own < min_variable {
+            Err(format!(
+                "the workload needs at least {} variables besides the fences, got {}",
+                min_variable, n_own
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    // written values are unique per variable, counting from 1; fences take
+    // the last `n_node` variables and come on top of `n_transaction`
+    pub fn generate<R: Rng>(
+        &self,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        rng: &mut R,
+    ) -> Vec<Session> {
+        if self.fence == 0 {
+            return self.generate_unfenced(n_node, n_variable, n_transaction, rng);
+        }
+
+        // the sessions take the last variables
+        let n_own = std::cmp::max(n_variable, n_node + 1) - n_node;
+        let sessions = self.generate_unfenced(n_node, n_own, n_transaction, rng);
+
+        // applications number their own variables
+        let first_fence = sessions
+            .iter()
+            .flat_map(|session| session.iter())
+            .flat_map(|transaction| transaction.events.iter())
+            .map(|event| event.variable + 1)
+            .fold(n_own, std::cmp::max);
+
+        sessions
+            .into_iter()
+            .enumerate()
+            .map(|(i_node, session)| {
+                let mut fenced = Vec::new();
+                for (i_transaction, transaction) in session.into_iter().enumerate() {
+                    fenced.push(transaction);
+                    if (i_transaction + 1) % self.fence == 0 {
+                        let mut events: Vec<Event> = (0..n_node)
+                            .filter(|&i| i != i_node)
+                            .map(|i| Event::read(first_fence + i))
+                            .collect();
+                        events.push(Event::write(
+                            first_fence + i_node,
+                            (i_transaction + 1) / self.fence,
+                        ));
+                        fenced.push(Transaction {
+                            events,
+                            success: false,
+                        });
+                    }
+                }
+                fenced
+            })
+            .collect()
+    }
+
+    fn generate_unfenced<R: Rng>(
+        &self,
+        n_node: usize,
+        n_variable: usize,
+        n_transaction: usize,
+        rng: &mut R,
+    ) -> Vec<Session> {
+        if let Some(preset) = self.preset {
+            return preset.generate(n_node, n_variable, n_transaction, rng);
+        }
+        if let Some(application) = self.application {
+            return application.generate(n_node, n_transaction, rng);
+        }
+
+        let mut counters = HashMap::new();
+        let read_keys = KeyChooser::new(self.keys, n_variable, 0..n_variable);
+        let n_event_range = Uniform::from(self.min_event..=self.max_event);
+        let jump = (n_variable as f64 / n_node as f64).ceil() as usize;
+
+        (0..n_node)
+            .map(|i_node| {
+                let i = std::cmp::min(i_node * jump, n_variable);
+                let j = std::cmp::min((i_node + 1) * jump, n_variable);
+                // sessions past the last variable share all of them
+                let write_keys = if self.partition && i < j {
+                    KeyChooser::new(self.keys, n_variable, i..j)
+                } else {
+                    KeyChooser::new(self.keys, n_variable, 0..n_variable)
+                };
+
+                (0..n_transaction)
+                    .map(|_| {
+                        let n_event = n_event_range.sample(rng);
+                        let kind: f64 = rng.gen();
+                        let read_ratio = if kind < self.read_only {
+                            1.0
+                        } else if kind < self.read_only + self.write_only {
+                            0.0
+                        } else {
+                            self.read_ratio
+                        };
+
+                        let mut events = Vec::with_capacity(n_event);
+                        while events.len() < n_event {
+                            if rng.gen::<f64>() < read_ratio {
+                                events.push(Event::read(read_keys.sample(rng)));
+                            } else {
+                                let variable = write_keys.sample(rng);
+                                let value = {
+                                    let entry = counters.entry(variable).or_insert(0);
+                                    *entry += 1;
+                                    *entry
+                                };
+                                if read_ratio > 0.0
+                                    && events.len() + 1 < n_event
+                                    && rng.gen::<f64>() < self.read_modify_write
+                                {
+                                    events.push(Event::read(variable));
+                                }
+                                events.push(Event::write(variable, value));
+                            }
+                        }
+
+                        Transaction {
+                            events,
+                            success: false,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
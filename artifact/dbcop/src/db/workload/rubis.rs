@@ -0,0 +1,99 @@
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use db::history::{Session, Transaction};
+
+use super::{pick, KeySpace};
+
+// tables
+const USER: usize = 0;
+const USER_ITEMS: usize = 1;
+const USER_BIDS: usize = 2;
+const ITEM: usize = 3;
+const ITEM_MAX_BID: usize = 4;
+const ITEM_N_BIDS: usize = 5;
+const BID: usize = 6;
+const COMMENT: usize = 7;
+
+// view item, view user, place bid, comment, register item, register user
+const MIX: [usize; 6] = [40, 15, 20, 10, 10, 5];
+
+// every transaction acts for a random user; there is an item per user to
+// begin with
+pub fn generate<R: Rng>(
+    n_node: usize,
+    users: usize,
+    n_transaction: usize,
+    rng: &mut R,
+) -> Vec<Session> {
+    let mut keys = KeySpace::default();
+
+    // users, items, bids and comments, as the generator sees them
+    let mut n_user = std::cmp::max(users, 1);
+    let mut n_bid = vec![0; n_user];
+    let mut n_comment = vec![0; n_user];
+
+    let mut sessions: Vec<Session> = vec![Vec::new(); n_node];
+
+    for _ in 0..n_transaction {
+        for session in sessions.iter_mut() {
+            let u = Uniform::from(0..n_user).sample(rng);
+            let i = Uniform::from(0..n_bid.len()).sample(rng);
+
+            let mut events = Vec::new();
+
+            match pick(&MIX, rng) {
+                0 => {
+                    events.push(keys.read((ITEM, i, 0)));
+                    events.push(keys.read((ITEM_MAX_BID, i, 0)));
+                    events.push(keys.read((ITEM_N_BIDS, i, 0)));
+                }
+                1 => {
+                    events.push(keys.read((USER, u, 0)));
+                    events.push(keys.read((USER_ITEMS, u, 0)));
+                }
+                2 => {
+                    events.push(keys.read((ITEM, i, 0)));
+                    events.push(keys.read((ITEM_MAX_BID, i, 0)));
+                    events.push(keys.write((ITEM_MAX_BID, i, 0)));
+                    events.push(keys.read((ITEM_N_BIDS, i, 0)));
+                    events.push(keys.write((ITEM_N_BIDS, i, 0)));
+                    events.push(keys.write((BID, i, n_bid[i])));
+                    events.push(keys.read((USER_BIDS, u, 0)));
+                    events.push(keys.write((USER_BIDS, u, 0)));
+                    n_bid[i] += 1;
+                }
+                3 => {
+                    // rates the seller of the item
+                    let seller = i % n_user;
+                    events.push(keys.read((USER, seller, 0)));
+                    events.push(keys.write((USER, seller, 0)));
+                    events.push(keys.write((COMMENT, seller, n_comment[seller])));
+                    n_comment[seller] += 1;
+                }
+                4 => {
+                    let new_item = n_bid.len();
+                    events.push(keys.write((ITEM, new_item, 0)));
+                    events.push(keys.write((ITEM_MAX_BID, new_item, 0)));
+                    events.push(keys.write((ITEM_N_BIDS, new_item, 0)));
+                    events.push(keys.read((USER_ITEMS, u, 0)));
+                    events.push(keys.write((USER_ITEMS, u, 0)));
+                    n_bid.push(0);
+                }
+                _ => {
+                    events.push(keys.write((USER, n_user, 0)));
+                    events.push(keys.write((USER_ITEMS, n_user, 0)));
+                    n_user += 1;
+                    n_comment.push(0);
+                }
+            }
+
+            session.push(Transaction {
+                events,
+                success: false,
+            });
+        }
+    }
+
+    sessions
+}
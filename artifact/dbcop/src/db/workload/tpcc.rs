@@ -0,0 +1,156 @@
+use hashbrown::HashMap;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use db::history::{Session, Transaction};
+
+use super::{pick, KeySpace};
+
+// scaled down from the specification's 10 districts, 3000 customers and
+// 100000 items
+const N_DISTRICT: usize = 10;
+const N_CUSTOMER: usize = 30;
+const N_ITEM: usize = 100;
+
+// tables, split by the columns the transactions update
+const WAREHOUSE_YTD: usize = 0;
+const DISTRICT_NEXT_O_ID: usize = 1;
+const DISTRICT_YTD: usize = 2;
+const CUSTOMER: usize = 3;
+const ITEM: usize = 4;
+const STOCK: usize = 5;
+const ORDER: usize = 6;
+const NEW_ORDER: usize = 7;
+const ORDER_LINE: usize = 8;
+const HISTORY: usize = 9;
+
+// new order, payment, order status, delivery, stock level
+const MIX: [usize; 5] = [45, 43, 4, 4, 4];
+
+// orders the generator has placed so far, to pick the rows the other
+// transactions read
+#[derive(Default)]
+struct Orders {
+    // customer and items of every order of a district
+    placed: HashMap<usize, Vec<(usize, Vec<usize>)>>,
+    delivered: HashMap<usize, usize>,
+    last_of: HashMap<(usize, usize), usize>,
+}
+
+// every session is a terminal of the warehouse `i_node % warehouses`
+pub fn generate<R: Rng>(
+    n_node: usize,
+    warehouses: usize,
+    n_transaction: usize,
+    rng: &mut R,
+) -> Vec<Session> {
+    let warehouses = std::cmp::max(warehouses, 1);
+    let mut keys = KeySpace::default();
+    let mut orders = Orders::default();
+    let districts = Uniform::from(0..N_DISTRICT);
+    let customers = Uniform::from(0..N_CUSTOMER);
+    let items = Uniform::from(0..N_ITEM);
+    let n_line = Uniform::from(5..=15);
+
+    let mut sessions: Vec<Session> = vec![Vec::new(); n_node];
+
+    // round robin, so that order ids follow the order of the terminals
+    for _ in 0..n_transaction {
+        for (i_node, session) in sessions.iter_mut().enumerate() {
+            let w = i_node % warehouses;
+            let d = w * N_DISTRICT + districts.sample(rng);
+            let c = customers.sample(rng);
+
+            let mut events = Vec::new();
+
+            match pick(&MIX, rng) {
+                0 => {
+                    let lines: Vec<usize> =
+                        (0..n_line.sample(rng)).map(|_| items.sample(rng)).collect();
+                    let placed = orders.placed.entry(d).or_insert_with(Vec::new);
+                    let o = placed.len();
+
+                    events.push(keys.read((WAREHOUSE_YTD, w, 0)));
+                    events.push(keys.read((DISTRICT_NEXT_O_ID, d, 0)));
+                    events.push(keys.write((DISTRICT_NEXT_O_ID, d, 0)));
+                    events.push(keys.read((CUSTOMER, d, c)));
+                    events.push(keys.write((ORDER, d, o)));
+                    events.push(keys.write((NEW_ORDER, d, o)));
+                    for (i_line, &i) in lines.iter().enumerate() {
+                        events.push(keys.read((ITEM, i, 0)));
+                        events.push(keys.read((STOCK, w, i)));
+                        events.push(keys.write((STOCK, w, i)));
+                        events.push(keys.write((ORDER_LINE, d, o * 16 + i_line)));
+                    }
+
+                    placed.push((c, lines));
+                    orders.last_of.insert((d, c), o);
+                }
+                1 => {
+                    events.push(keys.read((WAREHOUSE_YTD, w, 0)));
+                    events.push(keys.write((WAREHOUSE_YTD, w, 0)));
+                    events.push(keys.read((DISTRICT_YTD, d, 0)));
+                    events.push(keys.write((DISTRICT_YTD, d, 0)));
+                    events.push(keys.read((CUSTOMER, d, c)));
+                    events.push(keys.write((CUSTOMER, d, c)));
+                    events.push(keys.write((HISTORY, i_node, session.len())));
+                }
+                2 => {
+                    events.push(keys.read((CUSTOMER, d, c)));
+                    if let Some(&o) = orders.last_of.get(&(d, c)) {
+                        events.push(keys.read((ORDER, d, o)));
+                        for i_line in 0..orders.placed[&d][o].1.len() {
+                            events.push(keys.read((ORDER_LINE, d, o * 16 + i_line)));
+                        }
+                    }
+                }
+                3 => {
+                    // the oldest undelivered order of every district
+                    for d in w * N_DISTRICT..(w + 1) * N_DISTRICT {
+                        let o = *orders.delivered.get(&d).unwrap_or(&0);
+                        events.push(keys.read((NEW_ORDER, d, o)));
+                        let (c, n_line) =
+                            match orders.placed.get(&d).and_then(|placed| placed.get(o)) {
+                                Some(&(c, ref lines)) => (c, lines.len()),
+                                None => continue,
+                            };
+                        events.push(keys.write((NEW_ORDER, d, o)));
+                        events.push(keys.read((ORDER, d, o)));
+                        events.push(keys.write((ORDER, d, o)));
+                        for i_line in 0..n_line {
+                            events.push(keys.read((ORDER_LINE, d, o * 16 + i_line)));
+                            events.push(keys.write((ORDER_LINE, d, o * 16 + i_line)));
+                        }
+                        events.push(keys.read((CUSTOMER, d, c)));
+                        events.push(keys.write((CUSTOMER, d, c)));
+                        orders.delivered.insert(d, o + 1);
+                    }
+                }
+                _ => {
+                    // stock of the items of the last 20 orders
+                    events.push(keys.read((DISTRICT_NEXT_O_ID, d, 0)));
+                    let mut seen: Vec<usize> = orders
+                        .placed
+                        .get(&d)
+                        .map_or(&[][..], |placed| &placed[placed.len().saturating_sub(20)..])
+                        .iter()
+                        .flat_map(|(_, lines)| lines.iter().cloned())
+                        .collect();
+                    seen.sort();
+                    seen.dedup();
+                    for i in seen {
+                        events.push(keys.read((STOCK, w, i)));
+                    }
+                }
+            }
+
+            session.push(Transaction {
+                events,
+                success: false,
+            });
+        }
+    }
+
+    sessions
+}
@@ -0,0 +1,103 @@
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use db::history::{Session, Transaction};
+
+use super::{pick, KeySpace};
+
+// every user starts following these many others
+const N_FOLLOWING: usize = 5;
+// tweets shown per followed user and per profile
+const N_SHOWN: usize = 3;
+
+// tables
+const TWEET_COUNT: usize = 0;
+const TWEET: usize = 1;
+const FOLLOWING: usize = 2;
+const FOLLOWERS: usize = 3;
+
+// timeline, tweet, follow or unfollow, profile
+const MIX: [usize; 4] = [50, 30, 10, 10];
+
+// every transaction acts for a random user
+pub fn generate<R: Rng>(
+    n_node: usize,
+    users: usize,
+    n_transaction: usize,
+    rng: &mut R,
+) -> Vec<Session> {
+    let users = std::cmp::max(users, 2);
+    let mut keys = KeySpace::default();
+    let user_range = Uniform::from(0..users);
+
+    // the follow graph and tweet counts, as the generator sees them
+    let mut following: Vec<Vec<usize>> = (0..users)
+        .map(|u| {
+            let mut followed: Vec<usize> = (0..N_FOLLOWING)
+                .map(|_| user_range.sample(rng))
+                .filter(|&v| v != u)
+                .collect();
+            followed.sort();
+            followed.dedup();
+            followed
+        })
+        .collect();
+    let mut n_tweet = vec![0; users];
+
+    let mut sessions: Vec<Session> = vec![Vec::new(); n_node];
+
+    for _ in 0..n_transaction {
+        for session in sessions.iter_mut() {
+            let u = user_range.sample(rng);
+
+            let mut events = Vec::new();
+
+            match pick(&MIX, rng) {
+                0 => {
+                    events.push(keys.read((FOLLOWING, u, 0)));
+                    for &v in following[u].iter() {
+                        events.push(keys.read((TWEET_COUNT, v, 0)));
+                        for k in n_tweet[v] - std::cmp::min(n_tweet[v], N_SHOWN)..n_tweet[v] {
+                            events.push(keys.read((TWEET, v, k)));
+                        }
+                    }
+                }
+                1 => {
+                    events.push(keys.read((TWEET_COUNT, u, 0)));
+                    events.push(keys.write((TWEET_COUNT, u, 0)));
+                    events.push(keys.write((TWEET, u, n_tweet[u])));
+                    n_tweet[u] += 1;
+                }
+                2 => {
+                    let v = user_range.sample(rng);
+                    events.push(keys.read((FOLLOWING, u, 0)));
+                    events.push(keys.write((FOLLOWING, u, 0)));
+                    events.push(keys.read((FOLLOWERS, v, 0)));
+                    events.push(keys.write((FOLLOWERS, v, 0)));
+                    match following[u].iter().position(|&w| w == v) {
+                        Some(i) => {
+                            following[u].remove(i);
+                        }
+                        None if v != u => following[u].push(v),
+                        None => {}
+                    }
+                }
+                _ => {
+                    let v = user_range.sample(rng);
+                    events.push(keys.read((TWEET_COUNT, v, 0)));
+                    events.push(keys.read((FOLLOWERS, v, 0)));
+                    for k in n_tweet[v] - std::cmp::min(n_tweet[v], N_SHOWN)..n_tweet[v] {
+                        events.push(keys.read((TWEET, v, k)));
+                    }
+                }
+            }
+
+            session.push(Transaction {
+                events,
+                success: false,
+            });
+        }
+    }
+
+    sessions
+}
@@ -2,21 +2,34 @@ extern crate bincode;
 extern crate chrono;
 extern crate clap;
 extern crate dbcop;
+extern crate rand;
 extern crate rayon;
+extern crate serde_json;
 extern crate serde_yaml;
 
 // use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use clap::{App, AppSettings, Arg, SubCommand};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::BufWriter;
 
 use std::path::Path;
+use std::time::Duration;
 
 use std::fs;
 
+use dbcop::consistency::witness::VerifyError;
+use dbcop::db::cluster::Cluster;
+use dbcop::db::export::{export, to_text};
 use dbcop::db::history::generate_mult_histories;
-use dbcop::db::history::History;
+use dbcop::db::history::{find_history_file, History, HistoryFormat};
+use dbcop::db::import::{import, ImportFormat};
+use dbcop::db::memory::{Isolation, MemoryCluster};
+use dbcop::db::workload::{Application, KeyDistribution, Preset, Workload};
+use dbcop::verifier::batch;
+use dbcop::verifier::differential;
+use dbcop::verifier::minimize::minimize;
+use dbcop::verifier::online::OnlineVerifier;
 use dbcop::verifier::Verifier;
 
 fn main() {
@@ -69,6 +82,112 @@ fn main() {
                         .default_value("2")
                         .help("Number of events per transactions"),
                 )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .help("Seed of the generated histories, random if absent"),
+                )
+                .arg(
+                    Arg::with_name("max_event")
+                        .long("max_evt")
+                        .takes_value(true)
+                        .help("Pick the number of events per transaction between --nevt and this"),
+                )
+                .arg(
+                    Arg::with_name("keys")
+                        .long("keys")
+                        .default_value("uniform")
+                        .possible_values(&["uniform", "zipf", "hotspot"])
+                        .help("Distribution of the accessed variables"),
+                )
+                .arg(
+                    Arg::with_name("zipf_theta")
+                        .long("zipf_theta")
+                        .default_value("0.99")
+                        .help("Skew of the zipf distribution"),
+                )
+                .arg(
+                    Arg::with_name("hot_keys")
+                        .long("hot_keys")
+                        .default_value("1")
+                        .help("Number of hot variables of the hotspot distribution"),
+                )
+                .arg(
+                    Arg::with_name("hot_fraction")
+                        .long("hot_fraction")
+                        .default_value("0.9")
+                        .help("Fraction of the accesses to hot variables"),
+                )
+                .arg(
+                    Arg::with_name("read_only")
+                        .long("read_only")
+                        .default_value("0")
+                        .help("Fraction of read-only transactions"),
+                )
+                .arg(
+                    Arg::with_name("write_only")
+                        .long("write_only")
+                        .default_value("0")
+                        .help("Fraction of write-only transactions"),
+                )
+                .arg(
+                    Arg::with_name("read_ratio")
+                        .long("read_ratio")
+                        .default_value("0.5")
+                        .help("Chance of a read in the other transactions"),
+                )
+                .arg(
+                    Arg::with_name("read_modify_write")
+                        .long("rmw")
+                        .default_value("0")
+                        .help("Chance of reading a variable right before writing it"),
+                )
+                .arg(
+                    Arg::with_name("partition")
+                        .long("partition")
+                        .help("Every session writes its own slice of the variables only"),
+                )
+                .arg(
+                    Arg::with_name("preset")
+                        .long("preset")
+                        .takes_value(true)
+                        .possible_values(&[
+                            "write_skew",
+                            "long_fork",
+                            "lost_update",
+                            "causality_chain",
+                            "fractured_read",
+                        ])
+                        .help("Transactions provoking an anomaly, instead of the workload above"),
+                )
+                .arg(
+                    Arg::with_name("application")
+                        .long("app")
+                        .takes_value(true)
+                        .possible_values(&["tpcc", "twitter", "rubis"])
+                        .help("Transactions of a key-value TPC-C, Twitter or RUBiS, instead of the workload above"),
+                )
+                .arg(
+                    Arg::with_name("scale")
+                        .long("scale")
+                        .takes_value(true)
+                        .help("Number of warehouses for tpcc (1), of users for twitter and rubis (100)"),
+                )
+                .arg(
+                    Arg::with_name("fence")
+                        .long("fence")
+                        .default_value("0")
+                        .help("Add a fence transaction after every this many transactions of a session, on the last --nnode variables"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .default_value("bincode")
+                        .possible_values(&["bincode", "json", "yaml"])
+                        .help("File format of generated histories"),
+                )
                 .about("Generate histories"),
             SubCommand::with_name("verify")
                 .arg(
@@ -77,7 +196,7 @@ fn main() {
                         .short("d")
                         .takes_value(true)
                         .required(true)
-                        .help("Directory containing executed histories"),
+                        .help("Executed history file, or directory containing executed histories"),
                 )
                 .arg(
                     Arg::with_name("o_directory")
@@ -90,21 +209,231 @@ fn main() {
                 .arg(
                     Arg::with_name("sat")
                         .long("sat")
-                        .help("Use MiniSAT as backend"),
+                        .help("Use a SAT solver as backend"),
+                )
+                .arg(
+                    Arg::with_name("solver")
+                        .long("solver")
+                        .takes_value(true)
+                        .default_value("cdcl")
+                        .help("In-process `cdcl` solver, or a DIMACS solver binary (minisat, kissat, cadical)"),
+                )
+                .arg(
+                    Arg::with_name("sat_timeout")
+                        .long("sat_timeout")
+                        .takes_value(true)
+                        .help("Give up on the SAT solver after these many seconds"),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .long("timeout")
+                        .takes_value(true)
+                        .help("Report unknown after these many seconds per history"),
+                )
+                .arg(
+                    Arg::with_name("max_states")
+                        .long("max_states")
+                        .takes_value(true)
+                        .help("Report unknown after exploring these many linearization states"),
+                )
+                .arg(
+                    Arg::with_name("polygraph")
+                        .long("polygraph")
+                        .help("Use polygraph pruning for si and ser, with the SAT solver for the rest"),
+                )
+                .arg(
+                    Arg::with_name("replay")
+                        .long("replay")
+                        .conflicts_with("bicomponent")
+                        .help("Replays the reads against the commit order of a passing history"),
                 )
                 .arg(
                     Arg::with_name("bicomponent")
                         .long("bic")
                         .help("Use BiComponent"),
                 )
+                .arg(
+                    Arg::with_name("import")
+                        .long("import")
+                        .takes_value(true)
+                        .possible_values(&["text", "cobra", "elle"])
+                        .help("Import a PolySI text file, Cobra log directory or Elle EDN file"),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Report every anomaly instead of the minimum failed level"),
+                )
                 .arg(
                     Arg::with_name("consistency")
                         .long("cons")
                         .short("c")
                         .takes_value(true)
-                        .help("Check for mentioned consistency"),
+                        .possible_values(&["rc", "rr", "ra", "cc", "pre", "si", "ser"])
+                        .help("Check for mentioned consistency, or find the minimum failed level"),
                 )
                 .about("Verifies histories"),
+            SubCommand::with_name("export")
+                .arg(
+                    Arg::with_name("history")
+                        .long("history")
+                        .short("i")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Executed history file"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Output file, or directory for Cobra logs"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(&["text", "cobra", "elle"])
+                        .help("Export as a PolySI text file, Cobra log directory or Elle EDN file"),
+                )
+                .about("Exports a history for other checkers"),
+            SubCommand::with_name("differential")
+                .arg(
+                    Arg::with_name("o_directory")
+                        .long("out_dir")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory to output the results and regressions"),
+                )
+                .arg(
+                    Arg::with_name("n_history")
+                        .long("nhist")
+                        .short("h")
+                        .default_value("10")
+                        .help("Number of histories to generate"),
+                )
+                .arg(
+                    Arg::with_name("n_node")
+                        .long("nnode")
+                        .short("n")
+                        .default_value("3")
+                        .help("Number of nodes per history"),
+                )
+                .arg(
+                    Arg::with_name("n_variable")
+                        .long("nvar")
+                        .short("v")
+                        .default_value("5")
+                        .help("Number of variables per history"),
+                )
+                .arg(
+                    Arg::with_name("n_transaction")
+                        .long("ntxn")
+                        .short("t")
+                        .default_value("5")
+                        .help("Number of transactions per history"),
+                )
+                .arg(
+                    Arg::with_name("n_event")
+                        .long("nevt")
+                        .short("e")
+                        .default_value("2")
+                        .help("Number of events per transactions"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .help("Seed of the simulated executions, random if absent"),
+                )
+                .about("Compares the verifier backends on simulated executions"),
+            SubCommand::with_name("execute")
+                .arg(
+                    Arg::with_name("g_directory")
+                        .long("gen_dir")
+                        .short("d")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory of generated histories"),
+                )
+                .arg(
+                    Arg::with_name("o_directory")
+                        .long("out_dir")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory to output the executed histories and results"),
+                )
+                .arg(
+                    Arg::with_name("isolation")
+                        .long("isolation")
+                        .takes_value(true)
+                        .default_value("si")
+                        .possible_values(&["ser", "si", "psi", "cc", "rc", "lost_update", "unordered"])
+                        .help("Isolation of the in-memory store, buggy ones included"),
+                )
+                .arg(
+                    Arg::with_name("online")
+                        .long("online")
+                        .takes_value(true)
+                        .possible_values(&["cc", "pre", "si", "ser"])
+                        .help("Verify mentioned consistency while the histories execute"),
+                )
+                .arg(
+                    Arg::with_name("window")
+                        .long("window")
+                        .takes_value(true)
+                        .requires("online")
+                        .help("Check the online history and drop what every later transaction sees every these many transactions, 16 if absent"),
+                )
+                .about("Executes generated histories on the in-memory store"),
+            SubCommand::with_name("minimize")
+                .arg(
+                    Arg::with_name("history")
+                        .long("history")
+                        .short("i")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Executed history file"),
+                )
+                .arg(
+                    Arg::with_name("o_directory")
+                        .long("out_dir")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory to output minimized.txt and the results"),
+                )
+                .arg(
+                    Arg::with_name("import")
+                        .long("import")
+                        .takes_value(true)
+                        .possible_values(&["text", "cobra", "elle"])
+                        .help("Import a PolySI text file, Cobra log directory or Elle EDN file"),
+                )
+                .arg(
+                    Arg::with_name("sat")
+                        .long("sat")
+                        .help("Use a SAT solver as backend"),
+                )
+                .arg(
+                    Arg::with_name("polygraph")
+                        .long("polygraph")
+                        .help("Use polygraph pruning for si and ser, with the SAT solver for the rest"),
+                )
+                .arg(
+                    Arg::with_name("consistency")
+                        .long("cons")
+                        .short("c")
+                        .takes_value(true)
+                        .possible_values(&["rc", "rr", "ra", "cc", "pre", "si", "ser"])
+                        .help("Keep the violation of mentioned consistency, or of the minimum failed level"),
+                )
+                .about("Shrinks a failing history to a minimal one with the same violation"),
         ])
         .setting(AppSettings::SubcommandRequired);
 
@@ -118,71 +447,75 @@ fn main() {
                 fs::create_dir_all(dir).expect("failed to create directory");
             }
 
+            let n_event = matches.value_of("n_event").unwrap().parse().unwrap();
+
+            let workload = Workload {
+                keys: match matches.value_of("keys").unwrap() {
+                    "zipf" => KeyDistribution::Zipf(
+                        matches.value_of("zipf_theta").unwrap().parse().unwrap(),
+                    ),
+                    "hotspot" => KeyDistribution::Hotspot {
+                        hot: matches.value_of("hot_keys").unwrap().parse().unwrap(),
+                        fraction: matches.value_of("hot_fraction").unwrap().parse().unwrap(),
+                    },
+                    _ => KeyDistribution::Uniform,
+                },
+                read_only: matches.value_of("read_only").unwrap().parse().unwrap(),
+                write_only: matches.value_of("write_only").unwrap().parse().unwrap(),
+                read_ratio: matches.value_of("read_ratio").unwrap().parse().unwrap(),
+                min_event: n_event,
+                max_event: matches
+                    .value_of("max_event")
+                    .map_or(n_event, |n| n.parse().unwrap()),
+                read_modify_write: matches
+                    .value_of("read_modify_write")
+                    .unwrap()
+                    .parse()
+                    .unwrap(),
+                partition: matches.is_present("partition"),
+                preset: matches
+                    .value_of("preset")
+                    .map(|name| Preset::from_name(name).unwrap()),
+                application: matches.value_of("application").map(|name| {
+                    Application::from_name(
+                        name,
+                        matches.value_of("scale").map(|n| n.parse().unwrap()),
+                    )
+                    .unwrap()
+                }),
+                fence: matches.value_of("fence").unwrap().parse().unwrap(),
+            };
+
+            let n_node = matches.value_of("n_node").unwrap().parse().unwrap();
+            let n_variable = matches.value_of("n_variable").unwrap().parse().unwrap();
+
+            if let Err(reason) = workload.check(n_node, n_variable) {
+                clap::Error::with_description(&reason, clap::ErrorKind::InvalidValue).exit();
+            }
+
             let mut histories = generate_mult_histories(
                 matches.value_of("n_history").unwrap().parse().unwrap(),
-                matches.value_of("n_node").unwrap().parse().unwrap(),
-                matches.value_of("n_variable").unwrap().parse().unwrap(),
+                n_node,
+                n_variable,
                 matches.value_of("n_transaction").unwrap().parse().unwrap(),
-                matches.value_of("n_event").unwrap().parse().unwrap(),
+                &workload,
+                matches
+                    .value_of("seed")
+                    .map_or_else(rand::random, |seed| seed.parse().unwrap()),
             );
 
+            let format = HistoryFormat::from_name(matches.value_of("format").unwrap()).unwrap();
+
             for hist in histories.drain(..) {
-                let file = File::create(dir.join(format!("hist-{:05}.bincode", hist.get_id())))
-                    .expect("couldn't create bincode file");
-                let buf_writer = BufWriter::new(file);
-                bincode::serialize_into(buf_writer, &hist)
-                    .expect("dumping history to bincode file went wrong");
+                hist.save_as(
+                    &dir.join(format!("hist-{:05}.{}", hist.get_id(), format.extension())),
+                    format,
+                )
+                .expect("dumping history to file went wrong");
             }
         }
         ("verify", Some(matches)) => {
-            // let v_dir = Path::new(matches.value_of("v_directory").unwrap());
-            //
-            // let histories: Vec<History> = fs::read_dir(v_dir)
-            //     .expect("couldn't read history directory")
-            //     .filter_map(|entry_res| match entry_res {
-            //         Ok(ref entry) if entry.path().is_dir() => {
-            //             let file = File::open(entry.path().join("history.bincode")).unwrap();
-            //             let buf_reader = BufReader::new(file);
-            //             Some(bincode::deserialize_from(buf_reader).unwrap())
-            //         }
-            //         _ => None,
-            //     })
-            //     .collect();
-            //
-            // let o_dir = Path::new(matches.value_of("o_directory").unwrap());
-            //
-            // if !o_dir.is_dir() {
-            //     fs::create_dir_all(o_dir).expect("failed to create directory");
-            // }
-            //
-            // histories.iter().for_each(|ref hist| {
-            //     let curr_dir = o_dir.join(format!("hist-{:05}", hist.get_id()));
-            //
-            //     let mut verifier = Verifier::new(curr_dir.to_path_buf());
-            //
-            //     if matches.is_present("causal") {
-            //         verifier.model("cc");
-            //     } else if matches.is_present("snapshot_isolation") {
-            //         verifier.model("si");
-            //     } else if matches.is_present("serializable") {
-            //         verifier.model("ser");
-            //     }
-            //
-            //     verifier.sat(matches.is_present("sat"));
-            //     verifier.bicomponent(matches.is_present("bicomponent"));
-            //
-            //     if !verifier.transactional_history_verify(hist.get_data()) {
-            //         println!("hist-{:05} failed", hist.get_id());
-            //     } else {
-            //         println!("hist-{:05} done", hist.get_id());
-            //     }
-            // });
-
-            let v_path =
-                Path::new(matches.value_of("v_directory").unwrap()).join("history.bincode");
-            let file = File::open(v_path).unwrap();
-            let buf_reader = BufReader::new(file);
-            let hist: History = bincode::deserialize_from(buf_reader).unwrap();
+            let v_dir = Path::new(matches.value_of("v_directory").unwrap());
 
             let o_dir = Path::new(matches.value_of("o_directory").unwrap());
 
@@ -190,28 +523,242 @@ fn main() {
                 fs::create_dir_all(o_dir).expect("failed to create directory");
             }
 
-            // let curr_dir = o_dir.join(format!("hist-{:05}", hist.get_id()));
+            let model = matches.value_of("consistency").unwrap_or("");
+            let use_sat = matches.is_present("sat");
+            let solver = matches.value_of("solver").unwrap();
+            let sat_timeout = matches
+                .value_of("sat_timeout")
+                .map(|secs| Duration::from_secs(secs.parse().unwrap()));
+            let use_bicomponent = matches.is_present("bicomponent");
+            let use_polygraph = matches.is_present("polygraph");
+            let use_replay = matches.is_present("replay");
+            let timeout = matches
+                .value_of("timeout")
+                .map(|secs| Duration::from_secs(secs.parse().unwrap()));
+            let max_states = matches.value_of("max_states").map(|n| n.parse().unwrap());
+
+            let configure = |verifier: &mut Verifier| {
+                verifier.model(model);
+                verifier.sat(use_sat);
+                verifier.sat_solver(solver, sat_timeout);
+                verifier.bicomponent(use_bicomponent);
+                verifier.polygraph(use_polygraph);
+                verifier.replay(use_replay);
+                verifier.timeout(timeout);
+                verifier.max_states(max_states);
+            };
+
+            let v_path = if matches.is_present("import") || v_dir.is_file() {
+                Some(v_dir.to_path_buf())
+            } else {
+                find_history_file(v_dir)
+            };
+
+            if v_path.is_none() {
+                // a results tree of `hist-XXXXX` directories
+                if matches.is_present("report") {
+                    clap::Error::with_description(
+                        "--report takes a single history, not a directory of histories",
+                        clap::ErrorKind::ArgumentConflict,
+                    )
+                    .exit();
+                }
+
+                let histories = batch::load_histories(v_dir);
+
+                for unloadable in histories.iter().filter_map(|(_, hist)| hist.as_ref().err()) {
+                    println!(
+                        "{} unloadable - {}",
+                        unloadable.path.display(),
+                        unloadable.reason
+                    );
+                }
+
+                let summaries = batch::verify_all(&histories, o_dir, configure);
+
+                for summary in summaries.iter() {
+                    match summary.failed_level {
+                        Some(level) => {
+                            println!("{} failed - minimum level failed {:?}", summary.name, level)
+                        }
+                        None => println!("{} {}", summary.name, summary.verdict),
+                    }
+                }
+
+                batch::write_summary(o_dir, &summaries);
+
+                return;
+            }
+
+            let hist = match matches.value_of("import") {
+                Some(format) => import(v_dir, ImportFormat::from_name(format).unwrap())
+                    .expect("couldn't import history"),
+                None => History::load(&v_path.unwrap()).expect("couldn't load history file"),
+            };
 
             let mut verifier = Verifier::new(o_dir.to_path_buf());
 
-            match matches.value_of("consistency") {
-                Some("cc") => verifier.model("cc"),
-                Some("si") => verifier.model("si"),
-                Some("ser") => verifier.model("ser"),
-                None => verifier.model(""),
-                _ => unreachable!(),
+            configure(&mut verifier);
+
+            if matches.is_present("report") {
+                match verifier.anomaly_report(hist.get_data()) {
+                    Ok(report) => {
+                        let file = File::create(o_dir.join("anomaly_report.json"))
+                            .expect("couldn't create report file");
+                        serde_json::to_writer_pretty(BufWriter::new(file), &report)
+                            .expect("dumping report to json went wrong");
+                        println!("hist-{:05} anomalies {:?}", hist.get_id(), report.counts())
+                    }
+                    Err(e) => println!("hist-{:05} failed - {}", hist.get_id(), e),
+                }
+            } else {
+                match verifier.verify(hist.get_data()) {
+                    Err(ref e @ VerifyError::Unknown { .. }) => {
+                        println!("hist-{:05} {}", hist.get_id(), e)
+                    }
+                    Err(e) => println!("hist-{:05} failed - {}", hist.get_id(), e),
+                    Ok(_) => println!("hist-{:05} done", hist.get_id()),
+                }
+            }
+        }
+        ("export", Some(matches)) => {
+            let hist = History::load(Path::new(matches.value_of("history").unwrap()))
+                .expect("couldn't load history file");
+
+            export(
+                &hist,
+                Path::new(matches.value_of("output").unwrap()),
+                ImportFormat::from_name(matches.value_of("format").unwrap()).unwrap(),
+            )
+            .expect("couldn't export history");
+        }
+        ("differential", Some(matches)) => {
+            let o_dir = Path::new(matches.value_of("o_directory").unwrap());
+
+            if !o_dir.is_dir() {
+                fs::create_dir_all(o_dir).expect("failed to create directory");
+            }
+
+            let seed = matches
+                .value_of("seed")
+                .map_or_else(rand::random, |seed| seed.parse().unwrap());
+            println!("seed {}", seed);
+
+            let disagreements = differential::run(
+                matches.value_of("n_history").unwrap().parse().unwrap(),
+                matches.value_of("n_node").unwrap().parse().unwrap(),
+                matches.value_of("n_variable").unwrap().parse().unwrap(),
+                matches.value_of("n_transaction").unwrap().parse().unwrap(),
+                matches.value_of("n_event").unwrap().parse().unwrap(),
+                seed,
+                o_dir,
+            );
+
+            for disagreement in disagreements.iter() {
+                println!(
+                    "{} disagreement {:?} on {} transactions",
+                    disagreement.model,
+                    disagreement.verdicts,
+                    disagreement
+                        .history
+                        .iter()
+                        .map(|session| session.len())
+                        .sum::<usize>()
+                );
+            }
+            println!("{} disagreements", disagreements.len());
+        }
+        ("execute", Some(matches)) => {
+            let g_dir = Path::new(matches.value_of("g_directory").unwrap());
+
+            let o_dir = Path::new(matches.value_of("o_directory").unwrap());
+
+            let isolation = Isolation::from_name(matches.value_of("isolation").unwrap()).unwrap();
+            let window: usize = matches.value_of("window").unwrap_or("16").parse().unwrap();
+
+            let mut paths: Vec<_> = fs::read_dir(g_dir)
+                .expect("couldn't read history directory")
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let hist = History::load(&path).expect("couldn't load history file");
+                let n_session = hist.get_data().len();
+
+                let curr_dir = o_dir.join(format!("hist-{:05}", hist.get_id()));
+                fs::create_dir_all(&curr_dir).expect("failed to create directory");
+
+                let mut cluster = MemoryCluster::new(isolation, n_session);
+
+                match matches.value_of("online") {
+                    Some(model) => {
+                        let mut online =
+                            OnlineVerifier::new(curr_dir.clone(), model, n_session, window);
+                        match cluster.execute_online(&hist, &curr_dir, &mut online) {
+                            Err(ref e @ VerifyError::Unknown { .. }) => {
+                                println!("hist-{:05} {}", hist.get_id(), e)
+                            }
+                            Err(e) => println!("hist-{:05} failed - {}", hist.get_id(), e),
+                            Ok(_) => println!(
+                                "hist-{:05} done - {} transactions collected",
+                                hist.get_id(),
+                                online.n_collected()
+                            ),
+                        }
+                    }
+                    None => {
+                        cluster.execute(&hist, &curr_dir);
+                        println!("hist-{:05} executed", hist.get_id());
+                    }
+                }
+            }
+        }
+        ("minimize", Some(matches)) => {
+            let path = Path::new(matches.value_of("history").unwrap());
+
+            let o_dir = Path::new(matches.value_of("o_directory").unwrap());
+
+            if !o_dir.is_dir() {
+                fs::create_dir_all(o_dir).expect("failed to create directory");
+            }
+
+            let hist = match matches.value_of("import") {
+                Some(format) => import(path, ImportFormat::from_name(format).unwrap())
+                    .expect("couldn't import history"),
+                None => History::load(path).expect("couldn't load history file"),
             };
 
-            verifier.sat(matches.is_present("sat"));
-            verifier.bicomponent(matches.is_present("bicomponent"));
+            let model = matches.value_of("consistency").unwrap_or("");
+            let use_sat = matches.is_present("sat");
+            let use_polygraph = matches.is_present("polygraph");
+
+            let minimized = minimize(hist.get_data(), o_dir, |verifier: &mut Verifier| {
+                verifier.model(model);
+                verifier.sat(use_sat);
+                verifier.polygraph(use_polygraph);
+            });
+
+            let n_transaction =
+                |sessions: &[Vec<_>]| sessions.iter().map(|session| session.len()).sum::<usize>();
 
-            match verifier.verify(hist.get_data()) {
-                Some(level) => println!(
-                    "hist-{:05} failed - minimum level failed {:?}",
-                    hist.get_id(),
-                    level
-                ),
-                None => println!("hist-{:05} done", hist.get_id()),
+            match minimized {
+                Ok(Some((sessions, violation))) => {
+                    let file = File::create(o_dir.join("minimized.txt"))
+                        .expect("couldn't create minimized history file");
+                    to_text(&sessions, BufWriter::new(file))
+                        .expect("dumping minimized history went wrong");
+                    println!(
+                        "hist-{:05} minimized from {} to {} transactions - {}",
+                        hist.get_id(),
+                        n_transaction(hist.get_data()),
+                        n_transaction(&sessions),
+                        VerifyError::Violation(violation)
+                    );
+                }
+                Ok(None) => println!("hist-{:05} has no violation to minimize", hist.get_id()),
+                Err(e) => println!("hist-{:05} {}", hist.get_id(), e),
             }
         }
         _ => unreachable!(),
@@ -0,0 +1,175 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use consistency::witness::VerifyError;
+use consistency::Consistency;
+use db::history::{find_history_file, History};
+
+use super::Verifier;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Summary {
+    // directory of the history in the results tree, and of its results
+    pub name: String,
+    pub id: usize,
+    pub model: Consistency,
+    pub verdict: String,
+    pub failed_level: Option<Consistency>,
+    pub duration: f64,
+    pub n_session: usize,
+    pub n_transaction: usize,
+    pub n_event: usize,
+}
+
+// a history file that couldn't be loaded
+#[derive(Debug, Clone)]
+pub struct Unloadable {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+// histories executed by `Cluster::execute_all`, as `hist-XXXXX/history.{bincode,json,yaml}`,
+// with the name of their directory
+pub fn load_histories(v_dir: &Path) -> Vec<(String, Result<History, Unloadable>)> {
+    let mut paths: Vec<_> = fs::read_dir(v_dir)
+        .expect("couldn't read history directory")
+        .filter_map(|entry_res| match entry_res {
+            Ok(ref entry) if entry.path().is_dir() => find_history_file(&entry.path()),
+            _ => None,
+        })
+        .collect();
+
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap();
+            let hist = History::load(&path).map_err(|e| Unloadable {
+                reason: e.to_string(),
+                path,
+            });
+            (name, hist)
+        })
+        .collect()
+}
+
+// the results of every history go to the directory of the same name in
+// `o_dir`, as ids needn't be unique; a history that couldn't be loaded gets
+// the verdict `unloadable`
+pub fn verify_all<F>(
+    histories: &[(String, Result<History, Unloadable>)],
+    o_dir: &Path,
+    configure: F,
+) -> Vec<Summary>
+where
+    F: Fn(&mut Verifier) + Sync,
+{
+    let mut summaries: Vec<Summary> = histories
+        .par_iter()
+        .map(|(name, hist)| {
+            let curr_dir = o_dir.join(name);
+
+            if !curr_dir.is_dir() {
+                fs::create_dir_all(&curr_dir).expect("failed to create directory");
+            }
+
+            let mut verifier = Verifier::new(curr_dir);
+            configure(&mut verifier);
+
+            let hist = match hist {
+                Ok(hist) => hist,
+                Err(unloadable) => {
+                    info!(verifier.log, "couldn't load history";
+                        "path" => format!("{}", unloadable.path.display()),
+                        "reason" => &unloadable.reason,
+                    );
+                    return Summary {
+                        name: name.clone(),
+                        id: 0,
+                        model: verifier.consistency_model,
+                        verdict: "unloadable".to_string(),
+                        failed_level: None,
+                        duration: 0.0,
+                        n_session: 0,
+                        n_transaction: 0,
+                        n_event: 0,
+                    };
+                }
+            };
+
+            let moment = std::time::Instant::now();
+            let decision = verifier.verify(hist.get_data());
+            let duration = moment.elapsed();
+
+            let (verdict, failed_level) = match decision {
+                Ok(_) => ("ok", None),
+                Err(VerifyError::Violation(ref violation)) => ("failed", Some(violation.level)),
+                Err(VerifyError::Solver(_)) => ("error", None),
+                Err(VerifyError::Unknown { .. }) => ("unknown", None),
+                Err(_) => ("malformed", None),
+            };
+
+            Summary {
+                name: name.clone(),
+                id: hist.get_id(),
+                model: verifier.consistency_model,
+                verdict: verdict.to_string(),
+                failed_level,
+                duration: duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9,
+                n_session: hist.get_data().len(),
+                n_transaction: hist.get_data().iter().map(|session| session.len()).sum(),
+                n_event: hist
+                    .get_data()
+                    .iter()
+                    .flat_map(|session| session.iter())
+                    .map(|transaction| transaction.events.len())
+                    .sum(),
+            }
+        })
+        .collect();
+
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    summaries
+}
+
+pub fn write_summary(o_dir: &Path, summaries: &[Summary]) {
+    let file = File::create(o_dir.join("summary.json")).expect("couldn't create summary file");
+    serde_json::to_writer_pretty(BufWriter::new(file), summaries)
+        .expect("dumping summary to json went wrong");
+
+    let file = File::create(o_dir.join("summary.csv")).expect("couldn't create summary file");
+    let mut buf_writer = BufWriter::new(file);
+    writeln!(
+        buf_writer,
+        "name,id,model,verdict,failed_level,duration,n_session,n_transaction,n_event"
+    )
+    .expect("failed to write summary");
+    for summary in summaries.iter() {
+        writeln!(
+            buf_writer,
+            "{},{},{:?},{},{},{},{},{},{}",
+            summary.name,
+            summary.id,
+            summary.model,
+            summary.verdict,
+            summary
+                .failed_level
+                .map_or(String::new(), |level| format!("{:?}", level)),
+            summary.duration,
+            summary.n_session,
+            summary.n_transaction,
+            summary.n_event
+        )
+        .expect("failed to write summary");
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use consistency::certificate;
+use consistency::witness::VerifyError;
+use db::history::{generate_single_history, history_seed, History, Session};
+use db::memory::{Database, Isolation};
+use verifier::minimize::shrink;
+use verifier::Verifier;
+
+// levels compared between the backends, weakest first
+pub const MODELS: [&str; 5] = ["ra", "cc", "pre", "si", "ser"];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Disagreement {
+    pub model: String,
+    // (backend, verdict), with the expected verdict of the simulation as `simulated`
+    pub verdicts: Vec<(String, String)>,
+    pub history: Vec<Session>,
+}
+
+// executes generated sessions on `Database`, one step of a random session at a
+// time, with a replica per session if `isolation` replicates
+pub fn simulate<R: Rng>(sessions: &mut [Session], isolation: Isolation, rng: &mut R) {
+    let mut db = Database::new(isolation, sessions.len());
+    // next transaction and event of every session
+    let mut cursors = vec![(0, 0); sessions.len()];
+    let mut running = vec![None; sessions.len()];
+
+    loop {
+        let active: Vec<usize> = (0..sessions.len())
+            .filter(|&i| cursors[i].0 < sessions[i].len())
+            .collect();
+        if active.is_empty() {
+            break;
+        }
+        let i = active[Uniform::from(0..active.len()).sample(rng)];
+        let replica = db.replica_of(i);
+        if rng.gen() {
+            db.deliver(replica, rng);
+        }
+
+        let (i_transaction, i_event) = cursors[i];
+        db.step(
+            replica,
+            &mut running[i],
+            &mut sessions[i],
+            i_transaction,
+            i_event,
+        );
+        cursors[i] = if i_event < sessions[i][i_transaction].events.len() {
+            (i_transaction, i_event + 1)
+        } else {
+            (i_transaction + 1, 0)
+        };
+    }
+}
+
+fn verdict(sessions: &[Session], model: &str, dir: &Path, configure: fn(&mut Verifier)) -> String {
+    if !dir.is_dir() {
+        fs::create_dir_all(dir).expect("failed to create directory");
+    }
+
+    let mut verifier = Verifier::new(dir.to_path_buf());
+    verifier.model(model);
+    configure(&mut verifier);
+
+    match verifier.verify(sessions) {
+        Ok(Some(order)) => {
+            // the commit order of a pass has to hold up on its own
+            match certificate::check(sessions, &order, model == "si") {
+                Ok(_) => "ok".to_string(),
+                Err(e) => format!("bad certificate ({})", e),
+            }
+        }
+        Ok(None) => "ok".to_string(),
+        Err(VerifyError::Violation(_)) => "failed".to_string(),
+        Err(VerifyError::Solver(_)) => "error".to_string(),
+        Err(VerifyError::Unknown { .. }) => "unknown".to_string(),
+        Err(_) => "malformed".to_string(),
+    }
+}
+
+// verdict of every backend on `model`
+pub fn verdicts(sessions: &[Session], model: &str, dir: &Path) -> Vec<(String, String)> {
+    let mut backends: Vec<(&str, fn(&mut Verifier))> =
+        vec![("algo", |_| {}), ("sat", |verifier| verifier.sat(true))];
+    if model == "si" || model == "ser" {
+        backends.push(("polygraph", |verifier| verifier.polygraph(true)));
+    }
+
+    backends
+        .into_iter()
+        .map(|(name, configure)| {
+            (
+                name.to_string(),
+                verdict(sessions, model, &dir.join(name), configure),
+            )
+        })
+        .collect()
+}
+
+// whether an execution under `simulated` has to pass `model`
+fn implies(simulated: Isolation, model: &str) -> bool {
+    match simulated {
+        Isolation::Serializable => true,
+        Isolation::SnapshotIsolation => model != "ser",
+        Isolation::ParallelSnapshotIsolation | Isolation::Causal => model == "ra" || model == "cc",
+        _ => false,
+    }
+}
+
+fn disagrees(verdicts: &[(String, String)]) -> bool {
+    verdicts.windows(2).any(|w| w[0].1 != w[1].1)
+}
+
+// the verdicts of the backends, and of the simulation if it implies `model`
+fn model_verdicts(
+    sessions: &[Session],
+    model: &str,
+    simulated: Option<Isolation>,
+    dir: &Path,
+) -> Vec<(String, String)> {
+    let mut verdicts = verdicts(sessions, model, dir);
+    if simulated.map_or(false, |level| implies(level, model)) {
+        verdicts.push(("simulated".to_string(), "ok".to_string()));
+    }
+    verdicts
+}
+
+// the models the backends, or the simulation, disagree on
+pub fn compare(
+    sessions: &[Session],
+    simulated: Option<Isolation>,
+    dir: &Path,
+) -> Vec<Disagreement> {
+    MODELS
+        .iter()
+        .filter_map(|&model| {
+            let verdicts = model_verdicts(sessions, model, simulated, dir);
+            if disagrees(&verdicts) {
+                Some(Disagreement {
+                    model: model.to_string(),
+                    verdicts,
+                    history: sessions.to_vec(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// generates and simulates `n_hist` histories, cycling through the isolations
+// of `Database`, buggy ones included, the `i_hist`-th one from
+// `history_seed(seed, i_hist)`; every disagreement is minimized and dumped to `regression-XXXXX-<model>.json` in `dir`
+pub fn run(
+    n_hist: usize,
+    n_node: usize,
+    n_variable: usize,
+    n_transaction: usize,
+    n_event: usize,
+    seed: u64,
+    dir: &Path,
+) -> Vec<Disagreement> {
+    let levels = [
+        Isolation::Serializable,
+        Isolation::SnapshotIsolation,
+        Isolation::ParallelSnapshotIsolation,
+        Isolation::Causal,
+        Isolation::ReadCommitted,
+        Isolation::LostUpdate,
+        Isolation::UnorderedDelivery,
+    ];
+    let mut found = Vec::new();
+
+    for i_hist in 0..n_hist {
+        let level = levels[i_hist % levels.len()];
+        let hist_seed = history_seed(seed, i_hist);
+        let mut sessions =
+            generate_single_history(n_node, n_variable, n_transaction, n_event, hist_seed);
+        simulate(&mut sessions, level, &mut StdRng::seed_from_u64(hist_seed));
+
+        let curr_dir = dir.join(format!("hist-{:05}", i_hist));
+
+        for disagreement in compare(&sessions, Some(level), &curr_dir) {
+            let model = &disagreement.model;
+            let history = shrink(&disagreement.history, |sessions| {
+                disagrees(&model_verdicts(sessions, model, Some(level), &curr_dir))
+            })
+            .expect("simulated histories have unique writes");
+            let minimal = Disagreement {
+                verdicts: model_verdicts(&history, model, Some(level), &curr_dir),
+                model: model.clone(),
+                history,
+            };
+
+            let mut hist = History::from_data(minimal.history.clone());
+            hist.set_info(format!(
+                "{:?} execution of seed {}, {} {:?}",
+                level, hist_seed, minimal.model, minimal.verdicts
+            ));
+            hist.save(&dir.join(format!("regression-{:05}-{}.json", i_hist, minimal.model)))
+                .expect("dumping regression to json went wrong");
+
+            found.push(minimal);
+        }
+    }
+
+    found
+}
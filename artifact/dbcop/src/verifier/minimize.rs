@@ -0,0 +1,214 @@
+use std::mem;
+use std::path::Path;
+
+use hashbrown::{HashMap, HashSet};
+
+use consistency::witness::{VerifyError, Violation};
+use db::history::{Session, Transaction};
+
+use super::Verifier;
+
+type EventId = (usize, usize, usize);
+
+// shrinks a history failing the verifier to one failing with a violation of
+// the same level and kind. `None` if the history does not fail with a
+// violation, the history itself if it doesn't fail the same way without its
+// empty transactions
+pub fn minimize<F>(
+    histories: &[Session],
+    dir: &Path,
+    configure: F,
+) -> Result<Option<(Vec<Session>, Violation)>, VerifyError>
+where
+    F: Fn(&mut Verifier),
+{
+    let verify = |sessions: &[Session]| {
+        let mut verifier = Verifier::new(dir.to_path_buf());
+        configure(&mut verifier);
+        verifier.verify(sessions)
+    };
+
+    let violation = match verify(histories) {
+        Err(VerifyError::Violation(violation)) => violation,
+        Err(e) => return Err(e),
+        Ok(_) => return Ok(None),
+    };
+
+    let same = |sessions: &[Session]| match verify(sessions) {
+        Err(VerifyError::Violation(ref other)) => {
+            other.level == violation.level
+                && mem::discriminant(&other.witness) == mem::discriminant(&violation.witness)
+        }
+        _ => false,
+    };
+
+    let minimal = shrink(histories, same)?;
+
+    // leaves the counterexample of the minimal history in `dir`
+    match verify(&minimal) {
+        Err(VerifyError::Violation(minimal_violation)) => Ok(Some((minimal, minimal_violation))),
+        // the verdict changed between runs, e.g. on a timeout
+        _ => Ok(Some((histories.to_vec(), violation))),
+    }
+}
+
+// a smallest history `keeps` holds for, by delta debugging the sessions, then
+// transactions, then events of `histories`; reads whose write is dropped are
+// dropped along with it, so every remaining read still has its writer.
+// `histories` itself if `keeps` doesn't hold without its empty transactions
+pub fn shrink<F>(histories: &[Session], mut keeps: F) -> Result<Vec<Session>, VerifyError>
+where
+    F: FnMut(&[Session]) -> bool,
+{
+    let write_map = Verifier::gen_write_map(histories)?;
+
+    let mut kept: Vec<EventId> = histories
+        .iter()
+        .enumerate()
+        .flat_map(|(i_node, session)| {
+            session
+                .iter()
+                .enumerate()
+                .flat_map(move |(i_transaction, transaction)| {
+                    (0..transaction.events.len())
+                        .map(move |i_event| (i_node, i_transaction, i_event))
+                })
+        })
+        .collect();
+
+    // ddmin takes the units it starts with to pass
+    if !keeps(&restrict(histories, &write_map, kept.iter())) {
+        return Ok(histories.to_vec());
+    }
+
+    // sessions, transactions, then single events
+    let granularities: [fn(&EventId) -> EventId; 3] = [
+        |&(i_node, _, _)| (i_node, 0, 0),
+        |&(i_node, i_transaction, _)| (i_node, i_transaction, 0),
+        |&id| id,
+    ];
+
+    for unit_of in granularities.iter() {
+        let mut units: Vec<Vec<EventId>> = Vec::new();
+        for id in kept.iter() {
+            match units.last_mut() {
+                Some(ref mut unit) if unit_of(&unit[0]) == unit_of(id) => unit.push(*id),
+                _ => units.push(vec![*id]),
+            }
+        }
+
+        let units = ddmin(units, &mut |units: &[Vec<EventId>]| {
+            keeps(&restrict(histories, &write_map, units.iter().flatten()))
+        });
+        kept = units.into_iter().flatten().collect();
+    }
+
+    Ok(restrict(histories, &write_map, kept.iter()))
+}
+
+// the events `kept` of `histories`, without the reads of dropped writes and
+// the transactions and sessions left empty
+fn restrict<'a, I>(
+    histories: &[Session],
+    write_map: &HashMap<(usize, usize), EventId>,
+    kept: I,
+) -> Vec<Session>
+where
+    I: Iterator<Item = &'a EventId>,
+{
+    let kept: HashSet<EventId> = kept.cloned().collect();
+
+    histories
+        .iter()
+        .enumerate()
+        .filter_map(|(i_node, session)| {
+            let session: Session = session
+                .iter()
+                .enumerate()
+                .filter_map(|(i_transaction, transaction)| {
+                    let events: Vec<_> = transaction
+                        .events
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i_event, event)| {
+                            kept.contains(&(i_node, i_transaction, i_event))
+                                && (event.write
+                                    || match write_map.get(&(event.variable, event.value)) {
+                                        // the initial write is always there
+                                        Some(&(0, _, _)) | None => true,
+                                        Some(&(w_node, w_transaction, w_event)) => {
+                                            kept.contains(&(w_node - 1, w_transaction, w_event))
+                                        }
+                                    })
+                        })
+                        .map(|(_, event)| event.clone())
+                        .collect();
+                    if events.is_empty() {
+                        None
+                    } else {
+                        Some(Transaction {
+                            events,
+                            success: transaction.success,
+                        })
+                    }
+                })
+                .collect();
+            if session.is_empty() {
+                None
+            } else {
+                Some(session)
+            }
+        })
+        .collect()
+}
+
+// Zeller's ddmin: a 1-minimal subsequence of `units` that passes `test`,
+// given that all of them do
+fn ddmin<T, F>(mut units: Vec<T>, test: &mut F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&[T]) -> bool,
+{
+    let mut n = 2;
+
+    while units.len() >= 2 {
+        let size = (units.len() + n - 1) / n;
+        let chunks: Vec<&[T]> = units.chunks(size).collect();
+
+        let mut reduced = None;
+
+        for chunk in chunks.iter() {
+            if test(chunk) {
+                reduced = Some((chunk.to_vec(), 2));
+                break;
+            }
+        }
+
+        // with two chunks the complements are the chunks themselves
+        if reduced.is_none() && chunks.len() > 2 {
+            for i in 0..chunks.len() {
+                let complement: Vec<T> = chunks
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .flat_map(|(_, chunk)| chunk.iter().cloned())
+                    .collect();
+                if test(&complement) {
+                    reduced = Some((complement, std::cmp::max(n - 1, 2)));
+                    break;
+                }
+            }
+        }
+
+        match reduced {
+            Some((smaller, granularity)) => {
+                units = smaller;
+                n = granularity;
+            }
+            None if n >= units.len() => break,
+            None => n = std::cmp::min(2 * n, units.len()),
+        }
+    }
+
+    units
+}
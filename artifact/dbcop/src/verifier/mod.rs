@@ -3,27 +3,51 @@ use hashbrown::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use consistency::cdcl::CdclSolver;
+use consistency::certificate;
+use consistency::polygraph::Polygraph;
 use consistency::sat::Sat;
+use consistency::solver::{backend_from_name, SatBackend, SatError};
+use consistency::witness::{
+    to_dot, AnomalyReport, CommitOrder, VerifyError, Violation, Witness, WitnessEdge,
+};
 use consistency::Consistency;
 use db::history::Session;
 
 use consistency::algo::{
     AtomicHistoryPO, PrefixConsistentHistory, SerializableHistory, SnapshotIsolationHistory,
 };
-use consistency::util::ConstrainedLinearization;
+use consistency::util::{
+    Budget, ConstrainedLinearization, DiGraph, LinearizationError, OutOfBudget,
+};
 
+pub mod batch;
+pub mod differential;
+pub mod minimize;
+pub mod online;
 mod util;
 
 use self::util::{BiConn, UGraph};
 
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
 use slog::{Drain, Logger};
 
 pub struct Verifier {
     log: slog::Logger,
     consistency_model: Consistency,
     use_sat: bool,
+    sat_backend: Box<dyn SatBackend>,
     use_bicomponent: bool,
+    use_polygraph: bool,
+    use_replay: bool,
+    timeout: Option<Duration>,
+    max_states: Option<usize>,
+    budget: Budget,
     dir: PathBuf,
 }
 
@@ -36,7 +60,13 @@ impl Verifier {
             log: Self::get_logger(log_file),
             consistency_model: Consistency::Serializable,
             use_sat: false,
+            sat_backend: Box::new(CdclSolver::default()),
             use_bicomponent: false,
+            use_polygraph: false,
+            use_replay: false,
+            timeout: None,
+            max_states: None,
+            budget: Budget::default(),
             dir,
         }
     }
@@ -59,10 +89,39 @@ impl Verifier {
         self.use_sat = flag;
     }
 
+    pub fn sat_backend(&mut self, backend: Box<dyn SatBackend>) {
+        self.sat_backend = backend;
+    }
+
+    // external solvers work on files in the output directory
+    pub fn sat_solver(&mut self, name: &str, timeout: Option<Duration>) {
+        self.sat_backend = backend_from_name(name, &self.dir, timeout);
+    }
+
     pub fn bicomponent(&mut self, flag: bool) {
         self.use_bicomponent = flag;
     }
 
+    // wall-clock limit of each `verify`; past it, the verdict is unknown
+    pub fn timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // limit on the states memoized by a linearization search
+    pub fn max_states(&mut self, max_states: Option<usize>) {
+        self.max_states = max_states;
+    }
+
+    // only for snapshot isolation and serializability
+    pub fn polygraph(&mut self, flag: bool) {
+        self.use_polygraph = flag;
+    }
+
+    // re-checks every read against the commit order found on success
+    pub fn replay(&mut self, flag: bool) {
+        self.use_replay = flag;
+    }
+
     pub fn get_logger<W>(io: W) -> Logger
     where
         W: Write + Send + 'static,
@@ -80,21 +139,29 @@ impl Verifier {
         root_logger
     }
 
-    pub fn gen_write_map(histories: &[Session]) -> HashMap<(usize, usize), (usize, usize, usize)> {
+    pub fn gen_write_map(
+        histories: &[Session],
+    ) -> Result<HashMap<(usize, usize), (usize, usize, usize)>, VerifyError> {
         let mut write_map = HashMap::new();
 
         for (i_node, session) in histories.iter().enumerate() {
             for (i_transaction, transaction) in session.iter().enumerate() {
                 for (i_event, event) in transaction.events.iter().enumerate() {
                     if event.write {
-                        if write_map
-                            .insert(
-                                (event.variable, event.value),
-                                (i_node + 1, i_transaction, i_event),
-                            )
-                            .is_some()
-                        {
-                            panic!("each write should be unique");
+                        let curr = (i_node + 1, i_transaction, i_event);
+                        let prev = if event.value == 0 {
+                            // value 0 is reserved for the initial write
+                            Some((0, 0, 0))
+                        } else {
+                            write_map.insert((event.variable, event.value), curr)
+                        };
+                        if let Some(first) = prev {
+                            return Err(VerifyError::DuplicateWrite {
+                                variable: event.variable,
+                                value: event.value,
+                                first,
+                                second: curr,
+                            });
                         }
                     } else {
                         write_map.entry((event.variable, 0)).or_insert((0, 0, 0));
@@ -103,10 +170,10 @@ impl Verifier {
             }
         }
 
-        write_map
+        Ok(write_map)
     }
 
-    pub fn verify(&mut self, histories: &[Session]) -> Option<Consistency> {
+    pub fn verify(&mut self, histories: &[Session]) -> Result<Option<CommitOrder>, VerifyError> {
         let moment = std::time::Instant::now();
         let decision = self.transactional_history_verify(histories);
         let duration = moment.elapsed();
@@ -118,18 +185,230 @@ impl Verifier {
                 "model" => format!("{:?}", self.consistency_model),
                 "sat" => self.use_sat,
                 "bicomponent" => self.use_bicomponent,
+                "polygraph" => self.use_polygraph,
                 "duration" => duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9,
                 "minViolation" => match decision {
-                    Some(e) => format!("{:?}",e),
-                    None => format!("ok")
+                    Err(VerifyError::Violation(ref e)) => format!("{:?}",e.level),
+                    Err(VerifyError::Solver(_)) => "error".to_string(),
+                    Err(VerifyError::Unknown { ref reason, .. }) => format!("unknown ({})", reason),
+                    Err(_) => "malformed".to_string(),
+                    Ok(_) => "ok".to_string()
+                },
+                "witness" => match decision {
+                    Err(VerifyError::Violation(ref e)) => format!("{:?}",e.witness),
+                    Err(ref e) => format!("{:?}",e),
+                    Ok(_) => "none".to_string()
                 },
         );
 
         decision
     }
 
-    pub fn transactional_history_verify(&mut self, histories: &[Session]) -> Option<Consistency> {
-        let write_map = Self::gen_write_map(histories);
+    // scans the whole history instead of stopping at the first violation
+    pub fn anomaly_report(&mut self, histories: &[Session]) -> Result<AnomalyReport, VerifyError> {
+        let moment = std::time::Instant::now();
+
+        let write_map = Self::gen_write_map(histories)?;
+
+        self.start_budget();
+
+        let mut report = AnomalyReport::default();
+
+        for violation in self.read_anomalies(histories, &write_map, true) {
+            report.add(violation.witness);
+        }
+
+        let mut history = AtomicHistoryPO::new(Self::transaction_infos(histories, &write_map));
+
+        let ww = history
+            .saturate(Consistency::SnapshotIsolation, &self.budget)
+            .map_err(|e| self.out_of_budget(Consistency::SnapshotIsolation, e))?;
+        let rw = history.causal_rw(&ww);
+
+        for cycle in history.dependency_cycles(&ww, &rw) {
+            report.add(Witness::Cycle(cycle));
+        }
+
+        let duration = moment.elapsed();
+
+        info!(
+            self.log,
+            #"information",
+            "the anomaly report finished";
+                "duration" => duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9,
+                "counts" => format!("{:?}", report.counts()),
+        );
+
+        Ok(report)
+    }
+
+    // on success, the commit order justifying it, if the level has one
+    pub fn transactional_history_verify(
+        &mut self,
+        histories: &[Session],
+    ) -> Result<Option<CommitOrder>, VerifyError> {
+        let write_map = Self::gen_write_map(histories)?;
+
+        self.start_budget();
+
+        // non repeatable reads are allowed under read committed, so every
+        // read anomaly is needed to find one that is not
+        let read_committed = self.consistency_model == Consistency::ReadCommitted;
+        if let Some(violation) = self
+            .read_anomalies(histories, &write_map, read_committed)
+            .into_iter()
+            .find(|violation| !read_committed || violation.level == Consistency::ReadCommitted)
+        {
+            return Err(violation.into());
+        }
+
+        info!(self.log, "each read from latest write");
+        info!(self.log, "atomic reads");
+
+        let transaction_infos = Self::transaction_infos(histories, &write_map);
+
+        info!(self.log, "atleast not read commmitted";
+        "number of transactions" => format!("{}", transaction_infos.len())
+        );
+
+        if self.use_sat {
+            info!(self.log, "using SAT");
+        }
+
+        if self.use_polygraph {
+            info!(self.log, "using polygraph");
+        }
+
+        if self.use_bicomponent {
+            info!(self.log, "using bicomponent");
+        }
+
+        let decision = if self.use_bicomponent {
+            // communication graph
+            info!(self.log, "doing bicomponent decomposition");
+            let mut access_map = HashMap::new();
+            {
+                let mut access_vars = HashSet::new();
+                for (i_node, session) in histories.iter().enumerate() {
+                    for transaction in session.iter() {
+                        if transaction.success {
+                            for event in transaction.events.iter() {
+                                if event.success {
+                                    access_vars.insert(event.variable);
+                                }
+                            }
+                        }
+                    }
+                    for x in access_vars.drain() {
+                        access_map
+                            .entry(x)
+                            .or_insert_with(Vec::new)
+                            .push(i_node + 1);
+                    }
+                }
+            }
+
+            let mut ug: UGraph<usize> = Default::default();
+
+            for (_, ss) in access_map.drain() {
+                for &s1 in ss.iter() {
+                    for &s2 in ss.iter() {
+                        if s1 != s2 {
+                            ug.add_edge(s1, s2);
+                        }
+                    }
+                }
+            }
+
+            let biconn = BiConn::new(ug);
+
+            let biconnected_components = biconn.get_biconnected_vertex_components();
+
+            // the first failure stops the searches of the other components
+            let cancelled = Arc::new(AtomicBool::new(false));
+            self.budget.cancelled = Some(cancelled.clone());
+
+            let verifier = &*self;
+
+            biconnected_components
+                .par_iter()
+                .find_map_any(|component| {
+                    info!(verifier.log, "doing for component {:?}", component);
+                    let restrict_infos = verifier.restrict(&transaction_infos, component);
+
+                    match verifier
+                        .do_hard_verification(verifier.consistency_model, &restrict_infos)
+                    {
+                        // orders of components sharing sessions don't merge
+                        Ok(_) => None,
+                        // another component failed first
+                        Err(VerifyError::Unknown { .. }) if cancelled.load(Ordering::Relaxed) => {
+                            None
+                        }
+                        Err(e) => {
+                            cancelled.store(true, Ordering::Relaxed);
+                            Some(match e {
+                                VerifyError::Violation(mut violation) => {
+                                    let mut sessions: Vec<_> =
+                                        component.iter().cloned().collect();
+                                    sessions.sort_unstable();
+                                    info!(verifier.log, "component failed"; "sessions" => format!("{:?}", sessions));
+                                    violation.sessions = Some(sessions);
+                                    VerifyError::Violation(violation)
+                                }
+                                e => e,
+                            })
+                        }
+                    }
+                })
+                .map_or(Ok(None), Err)
+        } else {
+            self.do_hard_verification(self.consistency_model, &transaction_infos)
+        };
+
+        if let Ok(Some(ref order)) = decision {
+            if self.use_replay {
+                let snapshot_isolation = self.consistency_model == Consistency::SnapshotIsolation;
+                if let Err(e) = certificate::check(histories, order, snapshot_isolation) {
+                    info!(self.log, "replay failed"; "reason" => format!("{}", e));
+                    return Err(VerifyError::Unknown {
+                        level: self.consistency_model,
+                        reason: format!("commit order fails replay - {}", e),
+                    });
+                }
+                info!(self.log, "replay passed");
+            }
+            self.write_order(order);
+        }
+
+        if let Err(VerifyError::Violation(ref violation)) = decision {
+            if !violation.context.is_empty() {
+                let highlight = match violation.witness {
+                    Witness::Cycle(ref cycle) => cycle.clone(),
+                    _ => Vec::new(),
+                };
+                self.write_dot(&violation.context, &highlight);
+            }
+        }
+
+        decision
+    }
+
+    fn start_budget(&mut self) {
+        self.budget = Budget {
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            max_states: self.max_states,
+            cancelled: None,
+        };
+    }
+
+    fn read_anomalies(
+        &self,
+        histories: &[Session],
+        write_map: &HashMap<(usize, usize), (usize, usize, usize)>,
+        exhaustive: bool,
+    ) -> Vec<Violation> {
+        let mut anomalies = Vec::new();
 
         for (i_node_r, session) in histories.iter().enumerate() {
             for (i_transaction_r, transaction) in session.iter().enumerate() {
@@ -148,20 +427,38 @@ impl Verifier {
                                     // let event2 = &transaction2.events[i_event];
                                     // info!(self.log,"{:?}\n{:?}", event, event2);
                                     if !transaction2.success {
-                                        info!(
-                                            self.log,
-                                            "{:?} read from {:?}",
-                                            (i_node_r + 1, i_transaction_r, i_event_r),
-                                            (i_node, i_transaction, i_event),
-                                        );
-                                        info!(self.log, "finished early"; "reason" => "DIRTY READ", "description" => "read from uncommitted/aborted transaction");
-                                        return Some(Consistency::ReadCommitted);
+                                        anomalies.push(Violation::new(
+                                            Consistency::ReadCommitted,
+                                            Witness::AbortedRead {
+                                                read: (i_node_r + 1, i_transaction_r, i_event_r),
+                                                write: (i_node, i_transaction, i_event),
+                                            },
+                                        ));
+                                        if !exhaustive {
+                                            info!(
+                                                self.log,
+                                                "{:?} read from {:?}",
+                                                (i_node_r + 1, i_transaction_r, i_event_r),
+                                                (i_node, i_transaction, i_event),
+                                            );
+                                            info!(self.log, "finished early"; "reason" => "DIRTY READ", "description" => "read from uncommitted/aborted transaction");
+                                            return anomalies;
+                                        }
                                     }
                                 }
                             } else {
-                                info!(self.log, "finished early"; "reason" => "NO WRITE WITH SAME (VARIABLE, VALUE)");
-                                panic!("In consistent write");
-                                // return false;
+                                anomalies.push(Violation::new(
+                                    Consistency::ReadCommitted,
+                                    Witness::ThinAirRead {
+                                        read: (i_node_r + 1, i_transaction_r, i_event_r),
+                                        variable: event.variable,
+                                        value: event.value,
+                                    },
+                                ));
+                                if !exhaustive {
+                                    info!(self.log, "finished early"; "reason" => "NO WRITE WITH SAME (VARIABLE, VALUE)");
+                                    return anomalies;
+                                }
                             }
                         }
                     }
@@ -200,35 +497,62 @@ impl Verifier {
                                 writes.insert(event.variable, i_event);
                                 reads.remove(&event.variable);
                             } else {
-                                let &(wr_i_node, wr_i_transaction, wr_i_event) =
-                                    write_map.get(&(event.variable, event.value)).unwrap();
+                                let (wr_i_node, wr_i_transaction, wr_i_event) =
+                                    match write_map.get(&(event.variable, event.value)) {
+                                        Some(&wr_event) => wr_event,
+                                        // already reported as thin air read
+                                        None => continue,
+                                    };
                                 if let Some(pos) = writes.get(&event.variable) {
                                     // checking if read the last write in same transaction
                                     if !((i_node + 1 == wr_i_node)
                                         && (i_transaction == wr_i_transaction)
                                         && (*pos == wr_i_event))
                                     {
-                                        info!(
-                                            self.log,
-                                            "wr:{:?}, rd:{:?}",
-                                            (wr_i_node, wr_i_transaction, wr_i_event),
-                                            (i_node + 1, i_transaction, i_event)
-                                        );
-                                        info!(self.log, "finished early"; "reason" => "LOST UPDATE", "description" => "did not read the latest write within transaction");
-                                        return Some(Consistency::ReadCommitted);
+                                        anomalies.push(Violation::new(
+                                            Consistency::ReadCommitted,
+                                            Witness::LostUpdate {
+                                                read: (i_node + 1, i_transaction, i_event),
+                                                write: (wr_i_node, wr_i_transaction, wr_i_event),
+                                            },
+                                        ));
+                                        if !exhaustive {
+                                            info!(
+                                                self.log,
+                                                "wr:{:?}, rd:{:?}",
+                                                (wr_i_node, wr_i_transaction, wr_i_event),
+                                                (i_node + 1, i_transaction, i_event)
+                                            );
+                                            info!(self.log, "finished early"; "reason" => "LOST UPDATE", "description" => "did not read the latest write within transaction");
+                                            return anomalies;
+                                        }
                                     }
                                 } else {
                                     if event.value != 0 {
                                         // checking if read the last write from other transaction
-                                        if *transaction_last_writes
+                                        // aborted writers are already reported as dirty reads
+                                        if transaction_last_writes
                                             .get(&(wr_i_node, wr_i_transaction))
-                                            .unwrap()
-                                            .get(&event.variable)
-                                            .unwrap()
-                                            != wr_i_event
+                                            .and_then(|last_writes| {
+                                                last_writes.get(&event.variable)
+                                            })
+                                            .map_or(false, |&pos| pos != wr_i_event)
                                         {
-                                            info!(self.log, "finished early"; "reason" => "UNCOMMITTED READ", "description" => "read some non-last write from other transaction");
-                                            return Some(Consistency::ReadCommitted);
+                                            anomalies.push(Violation::new(
+                                                Consistency::ReadCommitted,
+                                                Witness::IntermediateRead {
+                                                    read: (i_node + 1, i_transaction, i_event),
+                                                    write: (
+                                                        wr_i_node,
+                                                        wr_i_transaction,
+                                                        wr_i_event,
+                                                    ),
+                                                },
+                                            ));
+                                            if !exhaustive {
+                                                info!(self.log, "finished early"; "reason" => "UNCOMMITTED READ", "description" => "read some non-last write from other transaction");
+                                                return anomalies;
+                                            }
                                         }
                                     }
 
@@ -240,8 +564,26 @@ impl Verifier {
                                             && (*wr_i_transaction2 == wr_i_transaction)
                                             && (*wr_i_event2 == wr_i_event))
                                         {
-                                            info!(self.log, "finished early"; "reason" => "NON REPEATABLE READ", "description" => "did not read same as latest read which is after lastest write");
-                                            return Some(Consistency::RepeatableRead);
+                                            anomalies.push(Violation::new(
+                                                Consistency::RepeatableRead,
+                                                Witness::NonRepeatableRead {
+                                                    read: (i_node + 1, i_transaction, i_event),
+                                                    write: (
+                                                        wr_i_node,
+                                                        wr_i_transaction,
+                                                        wr_i_event,
+                                                    ),
+                                                    previous_write: (
+                                                        *wr_i_node2,
+                                                        *wr_i_transaction2,
+                                                        *wr_i_event2,
+                                                    ),
+                                                },
+                                            ));
+                                            if !exhaustive {
+                                                info!(self.log, "finished early"; "reason" => "NON REPEATABLE READ", "description" => "did not read same as latest read which is after lastest write");
+                                                return anomalies;
+                                            }
                                         }
                                     }
                                 }
@@ -256,9 +598,13 @@ impl Verifier {
             }
         }
 
-        info!(self.log, "each read from latest write");
-        info!(self.log, "atomic reads");
+        anomalies
+    }
 
+    fn transaction_infos(
+        histories: &[Session],
+        write_map: &HashMap<(usize, usize), (usize, usize, usize)>,
+    ) -> HashMap<(usize, usize), (HashMap<usize, (usize, usize)>, HashSet<usize>)> {
         let mut transaction_infos = HashMap::new();
 
         let mut root_write_info = HashSet::new();
@@ -273,21 +619,28 @@ impl Verifier {
                             if event.write {
                                 write_info.insert(event.variable);
                             } else {
-                                let &(wr_i_node, wr_i_transaction, _) =
-                                    write_map.get(&(event.variable, event.value)).unwrap();
+                                let (wr_i_node, wr_i_transaction) =
+                                    match write_map.get(&(event.variable, event.value)) {
+                                        Some(&(wr_i_node, wr_i_transaction, _))
+                                            if wr_i_node == 0
+                                                || histories[wr_i_node - 1][wr_i_transaction]
+                                                    .success =>
+                                        {
+                                            (wr_i_node, wr_i_transaction)
+                                        }
+                                        // thin air or dirty read, only left when reporting
+                                        _ => continue,
+                                    };
                                 if event.value == 0 {
                                     assert_eq!(wr_i_node, 0);
                                     assert_eq!(wr_i_transaction, 0);
                                     root_write_info.insert(event.variable);
                                 }
                                 if wr_i_node != i_node + 1 || wr_i_transaction != i_transaction {
-                                    if let Some((old_i_node, old_i_transaction)) = read_info
-                                        .insert(event.variable, (wr_i_node, wr_i_transaction))
-                                    {
-                                        // should be same, because repeatable read
-                                        assert_eq!(old_i_node, wr_i_node);
-                                        assert_eq!(old_i_transaction, wr_i_transaction);
-                                    }
+                                    // same for every read, unless reporting non repeatable reads
+                                    read_info
+                                        .entry(event.variable)
+                                        .or_insert((wr_i_node, wr_i_transaction));
                                 }
                             }
                         }
@@ -305,134 +658,321 @@ impl Verifier {
                 .is_none());
         }
 
-        info!(self.log, "atleast not read commmitted";
-        "number of transactions" => format!("{}", transaction_infos.len())
-        );
+        transaction_infos
+    }
 
-        if self.use_sat {
-            info!(self.log, "using SAT");
-        }
+    fn restrict(
+        &self,
+        transaction_infos: &HashMap<
+            (usize, usize),
+            (HashMap<usize, (usize, usize)>, HashSet<usize>),
+        >,
+        component: &HashSet<usize>,
+    ) -> HashMap<(usize, usize), (HashMap<usize, (usize, usize)>, HashSet<usize>)> {
+        let mut new_info = transaction_infos.clone();
 
-        if self.use_bicomponent {
-            info!(self.log, "using bicomponent");
-        }
+        // the initial writes belong to every component
+        let keep = |k: &(usize, usize)| k.0 == 0 || component.contains(&k.0);
 
-        if self.use_bicomponent {
-            // communication graph
-            info!(self.log, "doing bicomponent decomposition");
-            let mut access_map = HashMap::new();
-            {
-                let mut access_vars = HashSet::new();
-                for (i_node, session) in histories.iter().enumerate() {
-                    for transaction in session.iter() {
-                        if transaction.success {
-                            for event in transaction.events.iter() {
-                                if event.success {
-                                    access_vars.insert(event.variable);
-                                }
-                            }
-                        }
-                    }
-                    for x in access_vars.drain() {
-                        access_map
-                            .entry(x)
-                            .or_insert_with(Vec::new)
-                            .push(i_node + 1);
-                    }
-                }
-            }
+        new_info.retain(|k, _| keep(k));
 
-            let mut ug: UGraph<usize> = Default::default();
+        new_info
+            .values_mut()
+            .for_each(|(read_info, _)| read_info.retain(|_, k| keep(k)));
 
-            for (_, ss) in access_map.drain() {
-                for &s1 in ss.iter() {
-                    for &s2 in ss.iter() {
-                        if s1 != s2 {
-                            ug.add_edge(s1, s2);
-                        }
-                    }
-                }
-            }
+        new_info
+    }
 
-            let biconn = BiConn::new(ug);
+    fn cycle_violation(
+        &self,
+        level: Consistency,
+        history: &AtomicHistoryPO,
+        ww: &HashMap<usize, DiGraph<(usize, usize)>>,
+        rw: &HashMap<usize, DiGraph<(usize, usize)>>,
+    ) -> Option<Violation> {
+        history.cycle_witness(ww, rw).map(|cycle| {
+            info!(self.log, "finished early"; "reason" => "CYCLE", "cycle" => format!("{:?}", cycle));
+            let vertices = cycle.iter().map(|&(u, _, _)| u).collect();
+            let context = history.subgraph(&vertices, ww, rw);
+            let mut violation = Violation::new(level, Witness::Cycle(cycle));
+            violation.context = context;
+            violation
+        })
+    }
 
-            let biconnected_components = biconn.get_biconnected_vertex_components();
+    fn linearization_violation(
+        &self,
+        level: Consistency,
+        history: &AtomicHistoryPO,
+        ww: &HashMap<usize, DiGraph<(usize, usize)>>,
+        rw: &HashMap<usize, DiGraph<(usize, usize)>>,
+        linearized: HashSet<(usize, usize)>,
+    ) -> Violation {
+        let mut stuck: Vec<_> = history
+            .txns_info
+            .keys()
+            .filter(|txn_id| !linearized.contains(txn_id))
+            .cloned()
+            .collect();
+        stuck.sort_unstable();
+        info!(self.log, "finished early"; "reason" => "NO LINEARIZATION", "remaining" => format!("{:?}", stuck));
+        let context = history.subgraph(&stuck.iter().cloned().collect(), ww, rw);
+        let mut violation = Violation::new(level, Witness::NonLinearizable(stuck));
+        violation.context = context;
+        violation
+    }
+
+    fn out_of_budget(&self, level: Consistency, e: OutOfBudget) -> VerifyError {
+        let reason = match e {
+            OutOfBudget::Timeout => "timeout",
+            OutOfBudget::States => "state budget",
+            OutOfBudget::Cancelled => "cancelled",
+        };
+        info!(self.log, "gave up"; "reason" => reason);
+        VerifyError::Unknown {
+            level,
+            reason: reason.to_string(),
+        }
+    }
 
-            if biconnected_components.iter().all(|component| {
-                info!(self.log, "doing for component {:?}", component);
-                let restrict_infos = self.restrict(&transaction_infos, component);
+    fn write_order(&self, order: &CommitOrder) {
+        let file = File::create(self.dir.join("commit_order.json"))
+            .expect("couldn't create commit order file");
+        serde_json::to_writer(BufWriter::new(file), order)
+            .expect("dumping commit order to json went wrong");
+    }
+
+    // counterexample graph next to the result log, render with `dot -Tsvg`
+    fn write_dot(&self, edges: &[WitnessEdge], highlight: &[WitnessEdge]) {
+        let mut file = File::create(self.dir.join("counterexample.dot"))
+            .expect("couldn't create counterexample file");
+        file.write_all(to_dot(edges, highlight).as_bytes())
+            .expect("failed to write counterexample");
+    }
+
+    fn sat_encoding(
+        level: Consistency,
+        transaction_infos: &HashMap<
+            (usize, usize),
+            (HashMap<usize, (usize, usize)>, HashSet<usize>),
+        >,
+    ) -> Sat {
+        let mut sat_solver = Sat::new(transaction_infos);
+
+        sat_solver.pre_vis_co();
+        sat_solver.session();
+        sat_solver.wr();
+        sat_solver.read_atomic();
 
-                self.do_hard_verification(&restrict_infos).is_none()
-            }) {
-                None
-            } else {
-                Some(self.consistency_model)
+        match level {
+            // already encoded by the clauses above
+            Consistency::ReadAtomic => {}
+            Consistency::Causal => {
+                sat_solver.vis_transitive();
             }
-        } else {
-            self.do_hard_verification(&transaction_infos)
+            Consistency::Prefix => {
+                sat_solver.prefix();
+            }
+            Consistency::SnapshotIsolation => {
+                sat_solver.prefix();
+                sat_solver.conflict();
+            }
+            Consistency::Serializable => {
+                sat_solver.ser();
+            }
+            _ => unreachable!(),
         }
+
+        sat_solver
     }
 
-    fn restrict(
+    // the solver only says unsatisfiable: a cycle of the saturated history if
+    // there is one, otherwise the sessions left after dropping every session
+    // whose removal keeps the encoding unsatisfiable
+    fn unsat_violation(
         &self,
+        level: Consistency,
         transaction_infos: &HashMap<
             (usize, usize),
             (HashMap<usize, (usize, usize)>, HashSet<usize>),
         >,
-        component: &HashSet<usize>,
-    ) -> HashMap<(usize, usize), (HashMap<usize, (usize, usize)>, HashSet<usize>)> {
-        let mut new_info = transaction_infos.clone();
+    ) -> Violation {
+        if let Ok((history, ww, rw)) =
+            Self::saturated(level, transaction_infos.clone(), &self.budget)
+        {
+            if let Some(violation) = self.cycle_violation(level, &history, &ww, &rw) {
+                return violation;
+            }
+        }
 
-        new_info.retain(|k, _| component.contains(&k.0));
+        let mut sessions: Vec<usize> = transaction_infos
+            .keys()
+            .map(|txn_id| txn_id.0)
+            .filter(|&i_node| i_node != 0)
+            .collect();
+        sessions.sort_unstable();
+        sessions.dedup();
 
-        new_info
-            .values_mut()
-            .for_each(|(read_info, _)| read_info.retain(|_, k| component.contains(&k.0)));
+        let mut i = 0;
+        while i < sessions.len() {
+            let rest = sessions
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &i_node)| i_node)
+                .collect();
+            let restrict_infos = self.restrict(transaction_infos, &rest);
+            match Self::sat_encoding(level, &restrict_infos).solve(&*self.sat_backend, &self.budget)
+            {
+                Ok(None) => {
+                    sessions.remove(i);
+                }
+                Ok(Some(_)) => i += 1,
+                // keeps the sessions left so far
+                Err(_) => break,
+            }
+        }
 
-        new_info
+        let core = self.restrict(transaction_infos, &sessions.into_iter().collect());
+        match Self::saturated(level, core.clone(), &self.budget) {
+            Ok((history, ww, rw)) => self.linearization_violation(
+                level,
+                &history,
+                &ww,
+                &rw,
+                Some((0, 0)).into_iter().collect(),
+            ),
+            // without the edges between them
+            Err(_) => {
+                let mut stuck: Vec<_> = core.keys().filter(|&&t| t != (0, 0)).cloned().collect();
+                stuck.sort_unstable();
+                Violation::new(level, Witness::NonLinearizable(stuck))
+            }
+        }
+    }
+
+    // the history with vis saturated for `level`, with its ww and rw edges
+    fn saturated(
+        level: Consistency,
+        transaction_infos: HashMap<
+            (usize, usize),
+            (HashMap<usize, (usize, usize)>, HashSet<usize>),
+        >,
+        budget: &Budget,
+    ) -> Result<
+        (
+            AtomicHistoryPO,
+            HashMap<usize, DiGraph<(usize, usize)>>,
+            HashMap<usize, DiGraph<(usize, usize)>>,
+        ),
+        OutOfBudget,
+    > {
+        let mut history = AtomicHistoryPO::new(transaction_infos);
+
+        let ww = if level == Consistency::ReadAtomic {
+            let wr = history.get_wr();
+            history.vis_includes(&wr);
+            let ww = history.causal_ww();
+            for (_, ww_x) in ww.iter() {
+                history.vis_includes(ww_x);
+            }
+            ww
+        } else {
+            history.saturate(level, budget)?
+        };
+
+        let rw = if level == Consistency::Serializable {
+            history.causal_rw(&ww)
+        } else {
+            Default::default()
+        };
+
+        Ok((history, ww, rw))
     }
 
     fn do_hard_verification(
-        &mut self,
+        &self,
+        level: Consistency,
         transaction_infos: &HashMap<
             (usize, usize),
             (HashMap<usize, (usize, usize)>, HashSet<usize>),
         >,
-    ) -> Option<Consistency> {
-        if self.use_sat {
-            let mut sat_solver = Sat::new(&transaction_infos);
+    ) -> Result<Option<CommitOrder>, VerifyError> {
+        match level {
+            // nothing to check beyond the read level pre-checks
+            Consistency::ReadCommitted | Consistency::RepeatableRead => return Ok(None),
+            Consistency::Inc => {
+                let mut order = None;
+                for &weaker in [
+                    Consistency::ReadAtomic,
+                    Consistency::Causal,
+                    Consistency::Prefix,
+                    Consistency::SnapshotIsolation,
+                    Consistency::Serializable,
+                ]
+                .iter()
+                {
+                    order = self.do_hard_verification(weaker, transaction_infos)?;
+                }
+                return Ok(order);
+            }
+            _ => {}
+        }
 
-            sat_solver.pre_vis_co();
-            sat_solver.session();
-            sat_solver.wr();
-            sat_solver.read_atomic();
+        if self.use_polygraph
+            && (level == Consistency::SnapshotIsolation || level == Consistency::Serializable)
+        {
+            info!(self.log, "using polygraph");
 
-            match self.consistency_model {
-                Consistency::Causal => {
-                    sat_solver.vis_transitive();
-                }
-                Consistency::Prefix => {
-                    sat_solver.prefix();
-                }
-                Consistency::SnapshotIsolation => {
-                    sat_solver.prefix();
-                    sat_solver.conflict();
+            let mut polygraph = Polygraph::new(
+                transaction_infos,
+                level == Consistency::SnapshotIsolation,
+                self.log.clone(),
+            );
+
+            return match polygraph.check(&*self.sat_backend, &self.budget) {
+                Ok(None) => Ok(Some(polygraph.commit_order())),
+                Ok(Some(witness)) => {
+                    let mut violation = Violation::new(level, witness);
+                    if let Witness::Cycle(ref cycle) = violation.witness {
+                        info!(self.log, "finished early"; "reason" => "CYCLE", "cycle" => format!("{:?}", cycle));
+                        violation.context = cycle.clone();
+                    }
+                    Err(violation.into())
                 }
-                Consistency::Serializable => {
-                    sat_solver.ser();
+                Err(SatError::Timeout) => Err(self.out_of_budget(level, OutOfBudget::Timeout)),
+                Err(SatError::Cancelled) => Err(self.out_of_budget(level, OutOfBudget::Cancelled)),
+                Err(e) => {
+                    info!(self.log, "sat solver failed"; "error" => format!("{}", e));
+                    Err(VerifyError::Solver(e))
                 }
-                _ => unreachable!(),
-            }
+            };
+        }
 
-            if sat_solver.solve(&self.dir).is_some() {
-                None
-            } else {
-                Some(self.consistency_model)
+        if self.use_sat {
+            let mut sat_solver = Self::sat_encoding(level, transaction_infos);
+
+            match sat_solver.solve(&*self.sat_backend, &self.budget) {
+                Ok(Some(co)) => Ok(match level {
+                    Consistency::Prefix | Consistency::SnapshotIsolation => {
+                        Some(CommitOrder::Snapshot(sat_solver.snapshots(&co)))
+                    }
+                    Consistency::Serializable => Some(CommitOrder::Serial(co)),
+                    // visibility is not a prefix of the commit order
+                    _ => None,
+                }),
+                Ok(None) => Err(self.unsat_violation(level, transaction_infos).into()),
+                Err(SatError::Timeout) => Err(self.out_of_budget(level, OutOfBudget::Timeout)),
+                Err(SatError::Cancelled) => Err(self.out_of_budget(level, OutOfBudget::Cancelled)),
+                Err(e) => {
+                    info!(self.log, "sat solver failed"; "error" => format!("{}", e));
+                    Err(VerifyError::Solver(e))
+                }
             }
         } else {
             info!(self.log, "using our algorithms");
 
-            match self.consistency_model {
+            let decision = match level {
                 Consistency::ReadAtomic => {
                     let mut ra_hist = AtomicHistoryPO::new(transaction_infos.clone());
 
@@ -445,50 +985,46 @@ impl Verifier {
                     }
                     // ra_hist.vis_is_trans();
 
-                    if ra_hist.vis.has_cycle() {
-                        Some(self.consistency_model)
-                    } else {
-                        None
-                    }
+                    self.cycle_violation(level, &ra_hist, &ww, &Default::default())
+                        .map_or(Ok(None), Err)
                 }
                 Consistency::Causal => {
                     let mut causal_hist = AtomicHistoryPO::new(transaction_infos.clone());
 
-                    let wr = causal_hist.get_wr();
-                    causal_hist.vis_includes(&wr);
-                    causal_hist.vis_is_trans();
-                    let ww = causal_hist.causal_ww();
-                    for (_, ww_x) in ww.iter() {
-                        causal_hist.vis_includes(ww_x);
-                    }
-                    causal_hist.vis_is_trans();
+                    let ww = causal_hist
+                        .saturate(Consistency::Causal, &self.budget)
+                        .map_err(|e| self.out_of_budget(level, e))?;
 
-                    if causal_hist.vis.has_cycle() {
-                        Some(self.consistency_model)
-                    } else {
-                        None
-                    }
+                    self.cycle_violation(level, &causal_hist, &ww, &Default::default())
+                        .map_or(Ok(None), Err)
                 }
                 Consistency::Prefix => {
                     let mut pre_hist =
                         PrefixConsistentHistory::new(transaction_infos.clone(), self.log.clone());
 
-                    let wr = pre_hist.history.get_wr();
-                    pre_hist.history.vis_includes(&wr);
-                    pre_hist.history.vis_is_trans();
-                    let ww = pre_hist.history.causal_ww();
-                    for (_, ww_x) in ww.iter() {
-                        pre_hist.history.vis_includes(ww_x);
-                    }
-                    pre_hist.history.vis_is_trans();
+                    let ww = pre_hist
+                        .history
+                        .saturate(Consistency::Prefix, &self.budget)
+                        .map_err(|e| self.out_of_budget(level, e))?;
 
-                    if pre_hist.history.vis.has_cycle() {
-                        Some(self.consistency_model)
+                    if let Some(violation) =
+                        self.cycle_violation(level, &pre_hist.history, &ww, &Default::default())
+                    {
+                        Err(violation)
                     } else {
-                        if pre_hist.get_linearization().is_some() {
-                            None
-                        } else {
-                            Some(self.consistency_model)
+                        match pre_hist.linearize(&self.budget) {
+                            Ok(linearization) => Ok(Some(CommitOrder::Snapshot(linearization))),
+                            Err(LinearizationError::NotFound(partial)) => Err(self
+                                .linearization_violation(
+                                    level,
+                                    &pre_hist.history,
+                                    &ww,
+                                    &Default::default(),
+                                    partial.iter().filter(|v| v.1).map(|v| v.0).collect(),
+                                )),
+                            Err(LinearizationError::OutOfBudget(e)) => {
+                                return Err(self.out_of_budget(level, e));
+                            }
                         }
                     }
                 }
@@ -496,22 +1032,29 @@ impl Verifier {
                     let mut si_hist =
                         SnapshotIsolationHistory::new(transaction_infos.clone(), self.log.clone());
 
-                    let wr = si_hist.history.get_wr();
-                    si_hist.history.vis_includes(&wr);
-                    si_hist.history.vis_is_trans();
-                    let ww = si_hist.history.causal_ww();
-                    for (_, ww_x) in ww.iter() {
-                        si_hist.history.vis_includes(ww_x);
-                    }
-                    si_hist.history.vis_is_trans();
+                    let ww = si_hist
+                        .history
+                        .saturate(Consistency::SnapshotIsolation, &self.budget)
+                        .map_err(|e| self.out_of_budget(level, e))?;
 
-                    if si_hist.history.vis.has_cycle() {
-                        Some(self.consistency_model)
+                    if let Some(violation) =
+                        self.cycle_violation(level, &si_hist.history, &ww, &Default::default())
+                    {
+                        Err(violation)
                     } else {
-                        if si_hist.get_linearization().is_some() {
-                            None
-                        } else {
-                            Some(self.consistency_model)
+                        match si_hist.linearize(&self.budget) {
+                            Ok(linearization) => Ok(Some(CommitOrder::Snapshot(linearization))),
+                            Err(LinearizationError::NotFound(partial)) => Err(self
+                                .linearization_violation(
+                                    level,
+                                    &si_hist.history,
+                                    &ww,
+                                    &Default::default(),
+                                    partial.iter().filter(|v| v.1).map(|v| v.0).collect(),
+                                )),
+                            Err(LinearizationError::OutOfBudget(e)) => {
+                                return Err(self.out_of_budget(level, e));
+                            }
                         }
                     }
                 }
@@ -519,85 +1062,41 @@ impl Verifier {
                     let mut ser_hist =
                         SerializableHistory::new(transaction_infos.clone(), self.log.clone());
 
-                    let wr = ser_hist.history.get_wr();
-                    ser_hist.history.vis_includes(&wr);
-                    ser_hist.history.vis_is_trans();
-                    let ww = ser_hist.history.causal_ww();
-                    for (_, ww_x) in ww.iter() {
-                        ser_hist.history.vis_includes(ww_x);
-                    }
-                    ser_hist.history.vis_is_trans();
+                    let ww = ser_hist
+                        .history
+                        .saturate(Consistency::Serializable, &self.budget)
+                        .map_err(|e| self.out_of_budget(level, e))?;
+
+                    // anti-dependencies are only sound to add under serializability
+                    let rw = ser_hist.history.causal_rw(&ww);
 
-                    if ser_hist.history.vis.has_cycle() {
-                        Some(self.consistency_model)
+                    if let Some(violation) =
+                        self.cycle_violation(level, &ser_hist.history, &ww, &rw)
+                    {
+                        Err(violation)
                     } else {
-                        // let lin_o = ser_hist.get_linearization();
-                        // {
-                        //     // checking correctness
-                        //     if let Some(ref lin) = lin_o {
-                        //         let mut curr_value_map: HashMap<usize, (usize, usize)> =
-                        //             Default::default();
-                        //         for txn_id in lin.iter() {
-                        //             let (read_info, write_info) =
-                        //                 transaction_infos.get(txn_id).unwrap();
-                        //             for (x, txn1) in read_info.iter() {
-                        //                 match curr_value_map.get(&x) {
-                        //                     Some(txn1_) => assert_eq!(txn1_, txn1),
-                        //                     _ => unreachable!(),
-                        //                 }
-                        //             }
-                        //             for &x in write_info.iter() {
-                        //                 curr_value_map.insert(x, *txn_id);
-                        //             }
-                        //             // if !write_info.is_empty() {
-                        //             //     println!("{:?}", txn_id);
-                        //             //     println!("{:?}", curr_value_map);
-                        //             // }
-                        //         }
-                        //     }
-                        // }
-                        // lin_o.is_some();
-
-                        if ser_hist.get_linearization().is_some() {
-                            None
-                        } else {
-                            Some(self.consistency_model)
+                        match ser_hist.linearize(&self.budget) {
+                            Ok(linearization) => Ok(Some(CommitOrder::Serial(linearization))),
+                            Err(LinearizationError::NotFound(partial)) => Err(self
+                                .linearization_violation(
+                                    level,
+                                    &ser_hist.history,
+                                    &ww,
+                                    &rw,
+                                    partial.into_iter().collect(),
+                                )),
+                            Err(LinearizationError::OutOfBudget(e)) => {
+                                return Err(self.out_of_budget(level, e));
+                            }
                         }
                     }
                 }
-                Consistency::Inc => {
-                    self.consistency_model = Consistency::ReadAtomic;
-                    let decision = self.do_hard_verification(transaction_infos);
-                    if decision.is_some() {
-                        return decision;
-                    }
-                    self.consistency_model = Consistency::Causal;
-                    let decision = self.do_hard_verification(transaction_infos);
-                    if decision.is_some() {
-                        return decision;
-                    }
-                    self.consistency_model = Consistency::Prefix;
-                    let decision = self.do_hard_verification(transaction_infos);
-                    if decision.is_some() {
-                        return decision;
-                    }
-                    self.consistency_model = Consistency::SnapshotIsolation;
-                    let decision = self.do_hard_verification(transaction_infos);
-                    if decision.is_some() {
-                        return decision;
-                    }
-                    self.consistency_model = Consistency::Serializable;
-                    let decision = self.do_hard_verification(transaction_infos);
-                    if decision.is_some() {
-                        return decision;
-                    }
-                    self.consistency_model = Consistency::Inc;
-                    None
-                }
                 _ => {
                     unreachable!();
                 }
-            }
+            };
+
+            decision.map_err(VerifyError::from)
         }
     }
 }
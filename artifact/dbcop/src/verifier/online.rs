@@ -0,0 +1,546 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use hashbrown::{HashMap, HashSet};
+
+use consistency::algo::AtomicHistoryPO;
+use consistency::witness::{EdgeKind, VerifyError, Violation, Witness};
+use consistency::Consistency;
+use db::history::Transaction;
+
+use super::Verifier;
+
+type TransactionId = (usize, usize);
+type EventId = (usize, usize, usize);
+
+// a write of a collected transaction
+struct Collected {
+    writer: TransactionId,
+    // transactions joined before it was collected
+    n_joined: usize,
+    // writes of the variable it was visible to
+    overwriters: Vec<TransactionId>,
+}
+
+// a write as the verifier has seen it
+struct Write {
+    event: EventId,
+    committed: bool,
+    // last write of its transaction to the variable
+    last: bool,
+}
+
+// verifies a history while it is executed, from the transactions of every
+// session in session order; a violation is reported once no transaction to
+// come can undo it. a transaction joins the history once it and every
+// transaction it reads from are there, so the history is always closed
+// under session order and reads; cycles of the saturation are checked on
+// every join, anything else every `window` joins.
+//
+// at those points, the transactions that the latest one of every unfinished
+// session sees are collected, as every transaction to come will see them;
+// Cobra's fences make sure there are such. the history without them still
+// fails only if the whole one does, and vis keeps the edges they implied.
+// a later read of a collected write is reported if it sees the write
+// overwritten; other violations that need collected transactions are missed.
+//
+// so the history, and the saturation on it, stays as small as the fences
+// let it, but memory is not bounded: `write_map` and `collected` keep an
+// entry for every write ever pushed, to find its writer and report
+// duplicates and overwritten reads
+pub struct OnlineVerifier {
+    verifier: Verifier,
+    level: Consistency,
+    window: usize,
+    history: AtomicHistoryPO,
+    // received transactions waiting for their predecessors or writers
+    pending: HashMap<usize, VecDeque<(usize, Transaction)>>,
+    // the last transaction of every session that joined, aborted or not
+    joined: HashMap<usize, usize>,
+    running: HashSet<usize>,
+    write_map: HashMap<(usize, usize), Write>,
+    // what every transaction in the history wrote, and when it joined
+    written: HashMap<TransactionId, Vec<(usize, usize)>>,
+    joined_at: HashMap<TransactionId, usize>,
+    collected: HashMap<(usize, usize), Collected>,
+    n_joined: usize,
+    n_collected: usize,
+}
+
+impl OnlineVerifier {
+    // `model` is `cc`, `pre`, `si` or `ser`, whose visibility is transitive
+    pub fn new(dir: PathBuf, model: &str, n_session: usize, window: usize) -> Self {
+        let mut verifier = Verifier::new(dir);
+        verifier.model(model);
+        let level = verifier.consistency_model;
+
+        assert!(
+            match level {
+                Consistency::Causal
+                | Consistency::Prefix
+                | Consistency::SnapshotIsolation
+                | Consistency::Serializable => true,
+                _ => false,
+            },
+            "no online verification for {:?}",
+            level
+        );
+
+        OnlineVerifier {
+            verifier,
+            level,
+            window: std::cmp::max(window, 1),
+            history: AtomicHistoryPO::new(Default::default()),
+            pending: HashMap::new(),
+            joined: HashMap::new(),
+            running: (1..=n_session).collect(),
+            write_map: HashMap::new(),
+            written: HashMap::new(),
+            joined_at: HashMap::new(),
+            collected: HashMap::new(),
+            n_joined: 0,
+            n_collected: 0,
+        }
+    }
+
+    // transactions in the history, besides the root
+    pub fn n_live(&self) -> usize {
+        self.history
+            .txns_info
+            .keys()
+            .filter(|&&txn_id| txn_id != self.history.root)
+            .count()
+    }
+
+    // transactions collected so far
+    pub fn n_collected(&self) -> usize {
+        self.n_collected
+    }
+
+    // the transaction `i_transaction` of the 1-based `i_node`, after the ones
+    // before it in the session
+    pub fn push(
+        &mut self,
+        (i_node, i_transaction): TransactionId,
+        transaction: Transaction,
+    ) -> Result<(), VerifyError> {
+        for (i_event, event) in transaction.events.iter().enumerate() {
+            if event.write {
+                let curr = (i_node, i_transaction, i_event);
+                let prev = if event.value == 0 {
+                    // value 0 is reserved for the initial write
+                    Some((0, 0, 0))
+                } else {
+                    let last = !transaction.events[i_event + 1..]
+                        .iter()
+                        .any(|later| later.write && later.variable == event.variable);
+                    self.write_map
+                        .insert(
+                            (event.variable, event.value),
+                            Write {
+                                event: curr,
+                                committed: transaction.success,
+                                last,
+                            },
+                        )
+                        .map(|write| write.event)
+                };
+                if let Some(first) = prev {
+                    return Err(VerifyError::DuplicateWrite {
+                        variable: event.variable,
+                        value: event.value,
+                        first,
+                        second: curr,
+                    });
+                }
+            }
+        }
+
+        self.pending
+            .entry(i_node)
+            .or_insert_with(VecDeque::new)
+            .push_back((i_transaction, transaction));
+
+        self.join_ready(false)
+    }
+
+    // no more transactions of the 1-based `i_node`
+    pub fn end_session(&mut self, i_node: usize) -> Result<(), VerifyError> {
+        self.running.remove(&i_node);
+        self.join_ready(false)
+    }
+
+    // the history is complete: reads of writes that never came are from thin
+    // air, and the rest joins whatever it reads from
+    pub fn finish(&mut self) -> Result<(), VerifyError> {
+        self.running.clear();
+
+        let mut sessions: Vec<_> = self.pending.keys().cloned().collect();
+        sessions.sort_unstable();
+        for &i_node in sessions.iter() {
+            for (i_transaction, transaction) in self.pending[&i_node].iter() {
+                if !transaction.success {
+                    continue;
+                }
+                for (i_event, event) in transaction.events.iter().enumerate() {
+                    if !event.write
+                        && event.success
+                        && event.value != 0
+                        && !self.write_map.contains_key(&(event.variable, event.value))
+                    {
+                        return Err(Violation::new(
+                            Consistency::ReadCommitted,
+                            Witness::ThinAirRead {
+                                read: (i_node, *i_transaction, i_event),
+                                variable: event.variable,
+                                value: event.value,
+                            },
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
+
+        self.join_ready(true)?;
+        self.check_window()
+    }
+
+    // joins every transaction that can, or all of them if `force`
+    fn join_ready(&mut self, force: bool) -> Result<(), VerifyError> {
+        loop {
+            let mut sessions: Vec<_> = self.pending.keys().cloned().collect();
+            sessions.sort_unstable();
+
+            let mut progress = false;
+            for i_node in sessions {
+                while let Some(i_transaction) = self.next_ready(i_node, force) {
+                    let (_, transaction) = self
+                        .pending
+                        .get_mut(&i_node)
+                        .and_then(|queue| queue.pop_front())
+                        .unwrap();
+                    self.join((i_node, i_transaction), &transaction)?;
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                return Ok(());
+            }
+        }
+    }
+
+    // the next transaction of the session, if every write it reads joined
+    fn next_ready(&self, i_node: usize, force: bool) -> Option<usize> {
+        let &(i_transaction, ref transaction) = self.pending.get(&i_node)?.front()?;
+
+        let next = self.joined.get(&i_node).map_or(0, |&i| i + 1);
+        assert_eq!(
+            i_transaction, next,
+            "transactions of a session out of order"
+        );
+
+        if force || !transaction.success {
+            return Some(i_transaction);
+        }
+
+        let ready = transaction.events.iter().all(|event| {
+            event.write
+                || !event.success
+                || event.value == 0
+                || match self.write_map.get(&(event.variable, event.value)) {
+                    Some(write) => {
+                        let writer = (write.event.0, write.event.1);
+                        // aborted and own writes are checked right away
+                        !write.committed
+                            || writer == (i_node, i_transaction)
+                            || self.joined.get(&writer.0).map_or(false, |&i| i >= writer.1)
+                    }
+                    None => false,
+                }
+        });
+
+        if ready {
+            Some(i_transaction)
+        } else {
+            None
+        }
+    }
+
+    fn join(
+        &mut self,
+        txn_id: TransactionId,
+        transaction: &Transaction,
+    ) -> Result<(), VerifyError> {
+        self.joined.insert(txn_id.0, txn_id.1);
+
+        if !transaction.success {
+            return Ok(());
+        }
+
+        let (i_node, i_transaction) = txn_id;
+
+        let mut read_info = HashMap::new();
+        let mut collected_reads = Vec::new();
+        let mut write_info = HashSet::new();
+        let mut written = Vec::new();
+
+        // the checks of `Verifier::read_anomalies`, for one transaction
+        let mut writes: HashMap<usize, usize> = HashMap::new();
+        let mut reads: HashMap<usize, EventId> = HashMap::new();
+
+        for (i_event, event) in transaction.events.iter().enumerate() {
+            if !event.success {
+                continue;
+            }
+            let curr = (i_node, i_transaction, i_event);
+            if event.write {
+                writes.insert(event.variable, i_event);
+                reads.remove(&event.variable);
+                write_info.insert(event.variable);
+                written.push((event.variable, event.value));
+                continue;
+            }
+
+            let (write, committed, last) = if event.value == 0 {
+                ((0, 0, 0), true, true)
+            } else {
+                match self.write_map.get(&(event.variable, event.value)) {
+                    Some(write) => (write.event, write.committed, write.last),
+                    // only joined by `finish`, which reported it
+                    None => continue,
+                }
+            };
+
+            if !committed {
+                return Err(Violation::new(
+                    Consistency::ReadCommitted,
+                    Witness::AbortedRead { read: curr, write },
+                )
+                .into());
+            }
+
+            if let Some(&pos) = writes.get(&event.variable) {
+                if write != (i_node, i_transaction, pos) {
+                    return Err(Violation::new(
+                        Consistency::ReadCommitted,
+                        Witness::LostUpdate { read: curr, write },
+                    )
+                    .into());
+                }
+            } else {
+                if !last {
+                    return Err(Violation::new(
+                        Consistency::ReadCommitted,
+                        Witness::IntermediateRead { read: curr, write },
+                    )
+                    .into());
+                }
+                if let Some(&previous_write) = reads.get(&event.variable) {
+                    if previous_write != write {
+                        return Err(Violation::new(
+                            Consistency::RepeatableRead,
+                            Witness::NonRepeatableRead {
+                                read: curr,
+                                write,
+                                previous_write,
+                            },
+                        )
+                        .into());
+                    }
+                }
+            }
+            reads.insert(event.variable, write);
+
+            if (write.0, write.1) == txn_id {
+                continue;
+            }
+
+            if write.0 != 0 && self.is_collected((write.0, write.1)) {
+                collected_reads.push((curr, write, event.variable, event.value));
+            } else {
+                read_info
+                    .entry(event.variable)
+                    .or_insert((write.0, write.1));
+            }
+        }
+
+        if read_info.is_empty() && write_info.is_empty() && collected_reads.is_empty() {
+            return Ok(());
+        }
+
+        self.history
+            .add_transaction(txn_id, (read_info, write_info));
+        self.written.insert(txn_id, written);
+        self.n_joined += 1;
+        self.joined_at.insert(txn_id, self.n_joined);
+
+        let ww = self.history.resaturate(self.level);
+
+        for (read, write, x, value) in collected_reads {
+            if let Some(violation) = self.overwritten_read(read, write, x, value) {
+                return Err(violation.into());
+            }
+        }
+
+        let rw = if self.level == Consistency::Serializable {
+            self.history.causal_rw(&ww)
+        } else {
+            Default::default()
+        };
+        if let Some(violation) = self
+            .verifier
+            .cycle_violation(self.level, &self.history, &ww, &rw)
+        {
+            return Err(violation.into());
+        }
+
+        if self.n_joined % self.window == 0 {
+            self.check_window()?;
+            self.collect();
+        }
+
+        Ok(())
+    }
+
+    // joined once, but no longer in the history
+    fn is_collected(&self, txn_id: TransactionId) -> bool {
+        self.joined.get(&txn_id.0).map_or(false, |&i| i >= txn_id.1)
+            && !self.history.txns_info.contains_key(&txn_id)
+    }
+
+    // `read` read the collected `write`, which it must not see overwritten:
+    // by a write collected after it, seen by all that joined since, or by one
+    // joined later, which saw it
+    fn overwritten_read(
+        &self,
+        read: EventId,
+        write: EventId,
+        x: usize,
+        value: usize,
+    ) -> Option<Violation> {
+        let collected = &self.collected[&(x, value)];
+        let writer = collected.writer;
+        let reader = (read.0, read.1);
+        let sees = |t: &TransactionId| self.history.vis.has_edge(t, &reader);
+
+        let overwriter = collected
+            .overwriters
+            .iter()
+            .find(|t| !self.history.txns_info.contains_key(t) || sees(t))
+            .cloned()
+            .or_else(|| {
+                self.history
+                    .txns_info
+                    .iter()
+                    .find(|&(t, (_, writes))| {
+                        writes.contains(&x)
+                            && self
+                                .joined_at
+                                .get(t)
+                                .map_or(false, |&n| n > collected.n_joined)
+                            && sees(t)
+                    })
+                    .map(|(&t, _)| t)
+            })?;
+
+        let mut violation = Violation::new(
+            self.level,
+            Witness::OverwrittenRead {
+                read,
+                write,
+                overwrite: overwriter,
+            },
+        );
+        violation.context = vec![
+            (writer, EdgeKind::WR(x), reader),
+            (writer, EdgeKind::WW(x), overwriter),
+            (reader, EdgeKind::RW(x), overwriter),
+        ];
+        Some(violation)
+    }
+
+    // the checks of the batch verifier on the history so far
+    fn check_window(&mut self) -> Result<(), VerifyError> {
+        let transaction_infos = self.history.txns_info.clone();
+        self.verifier
+            .do_hard_verification(self.level, &transaction_infos)
+            .map(|_| ())
+    }
+
+    // removes the transactions every transaction to come will see
+    fn collect(&mut self) {
+        let root = self.history.root;
+        let vis = &self.history.vis;
+        let sees = |t: &TransactionId, u: &TransactionId| t == u || vis.has_edge(t, u);
+
+        // the last transaction of every session with transactions to come,
+        // which need one in the history
+        let mut latest = Vec::new();
+        let mut sessions: Vec<usize> = self.running.iter().cloned().collect();
+        sessions.extend(
+            self.pending
+                .iter()
+                .filter(|(_, queue)| !queue.is_empty())
+                .map(|(&i_node, _)| i_node),
+        );
+        for i_node in sessions {
+            match self
+                .history
+                .txns_info
+                .keys()
+                .filter(|t| t.0 == i_node)
+                .max()
+            {
+                Some(&txn_id) => latest.push(txn_id),
+                None => return,
+            }
+        }
+        if latest.is_empty() {
+            return;
+        }
+
+        let collected: HashSet<TransactionId> = self
+            .history
+            .txns_info
+            .keys()
+            .filter(|&&t| t != root && latest.iter().all(|u| sees(&t, u)))
+            .cloned()
+            .collect();
+
+        if collected.is_empty() {
+            return;
+        }
+
+        // the writes every collected write is visible to, collected or not
+        for txn_id in collected.iter() {
+            for &(x, value) in self.written[txn_id].iter() {
+                let overwriters = self
+                    .history
+                    .txns_info
+                    .iter()
+                    .filter(|&(t, (_, writes))| writes.contains(&x) && vis.has_edge(txn_id, t))
+                    .map(|(&t, _)| t)
+                    .collect();
+                self.collected.insert(
+                    (x, value),
+                    Collected {
+                        writer: *txn_id,
+                        n_joined: self.n_joined,
+                        overwriters,
+                    },
+                );
+            }
+        }
+
+        info!(self.verifier.log, "collected"; "transactions" => collected.len());
+
+        self.history.remove_transactions(&collected);
+        for txn_id in collected.iter() {
+            self.written.remove(txn_id);
+            self.joined_at.remove(txn_id);
+        }
+        self.n_collected += collected.len();
+    }
+}
@@ -0,0 +1,64 @@
+extern crate dbcop;
+
+mod common;
+
+use std::fs;
+
+use dbcop::db::history::{generate_mult_histories, Event, History, Transaction};
+use dbcop::db::workload::Workload;
+use dbcop::verifier::batch::{load_histories, verify_all};
+
+use common::dir;
+
+#[test]
+fn unloadable_histories_get_a_summary() {
+    let dir = dir("unloadable");
+
+    let hist = &generate_mult_histories(1, 2, 2, 2, &Workload::fixed(2), 7)[0];
+    let hist_dir = dir.join("hist-00000");
+    fs::create_dir_all(&hist_dir).unwrap();
+    hist.save(&hist_dir.join("history.json")).unwrap();
+
+    let broken_dir = dir.join("hist-00001");
+    fs::create_dir_all(&broken_dir).unwrap();
+    fs::write(broken_dir.join("history.bincode"), b"not a history").unwrap();
+
+    let histories = load_histories(&dir);
+    assert_eq!(histories.len(), 2);
+
+    let summaries = verify_all(&histories, &dir.join("out"), |verifier| {
+        verifier.model("cc")
+    });
+
+    assert_eq!(summaries[0].name, "hist-00000");
+    assert_eq!(summaries[1].name, "hist-00001");
+    assert_eq!(summaries[1].verdict, "unloadable");
+}
+
+#[test]
+fn histories_with_the_same_id_keep_their_results_apart() {
+    let dir = dir("same-id");
+
+    // imported and hand written histories all have id 0
+    for name in ["imported-a", "imported-b"].iter() {
+        let hist = History::from_data(vec![vec![Transaction {
+            events: vec![Event::write(0, 1)],
+            success: true,
+        }]]);
+        let hist_dir = dir.join(name);
+        fs::create_dir_all(&hist_dir).unwrap();
+        hist.save(&hist_dir.join("history.json")).unwrap();
+    }
+
+    let histories = load_histories(&dir);
+    let out = dir.join("out");
+    let summaries = verify_all(&histories, &out, |verifier| verifier.model("cc"));
+
+    let names: Vec<_> = summaries
+        .iter()
+        .map(|summary| summary.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["imported-a", "imported-b"]);
+    assert!(summaries.iter().all(|summary| summary.id == 0));
+    assert!(out.join("imported-a").is_dir() && out.join("imported-b").is_dir());
+}
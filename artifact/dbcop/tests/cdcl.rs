@@ -0,0 +1,145 @@
+extern crate dbcop;
+extern crate rand;
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use dbcop::consistency::cdcl::CdclSolver;
+use dbcop::consistency::solver::{Clause, SatBackend, SatError};
+use dbcop::consistency::util::Budget;
+
+fn solve(n_variable: usize, clauses: &[Clause]) -> Option<Vec<bool>> {
+    CdclSolver::default()
+        .solve(n_variable, clauses, &Budget::default())
+        .unwrap()
+}
+
+fn satisfies(assignments: &[bool], clauses: &[Clause]) -> bool {
+    clauses
+        .iter()
+        .all(|clause| clause.iter().any(|&(sign, v)| assignments[v] == sign))
+}
+
+// `n + 1` pigeons in `n` holes; pigeon i is in hole j if variable i * n + j + 1
+fn pigeonhole(n: usize) -> (usize, Vec<Clause>) {
+    let var = |i: usize, j: usize| i * n + j + 1;
+    let mut clauses = Vec::new();
+    for i in 0..=n {
+        clauses.push((0..n).map(|j| (true, var(i, j))).collect());
+    }
+    for j in 0..n {
+        for i1 in 0..=n {
+            for i2 in i1 + 1..=n {
+                clauses.push(vec![(false, var(i1, j)), (false, var(i2, j))]);
+            }
+        }
+    }
+    ((n + 1) * n, clauses)
+}
+
+#[test]
+fn pigeonhole_is_unsatisfiable() {
+    let (n_variable, clauses) = pigeonhole(2);
+    assert_eq!(solve(n_variable, &clauses), None);
+}
+
+#[test]
+fn unit_propagation_conflict() {
+    let clauses = vec![
+        vec![(true, 1)],
+        vec![(false, 1), (true, 2)],
+        vec![(false, 2), (true, 3)],
+        vec![(false, 3)],
+    ];
+    assert_eq!(solve(3, &clauses), None);
+
+    let assignments = solve(3, &clauses[..3]).expect("satisfiable");
+    assert!(satisfies(&assignments, &clauses[..3]));
+}
+
+#[test]
+fn agrees_with_brute_force() {
+    let mut rng = StdRng::seed_from_u64(10);
+    let n_variable = 8;
+
+    for _ in 0..300 {
+        // around the satisfiability threshold of random 3-sat
+        let clauses: Vec<Clause> = (0..34)
+            .map(|_| {
+                (0..3)
+                    .map(|_| (rng.gen(), rng.gen_range(1, n_variable + 1)))
+                    .collect()
+            })
+            .collect();
+
+        let brute_force = (0..1u32 << n_variable).any(|bits| {
+            let assignments: Vec<bool> = (0..=n_variable)
+                .map(|v| v > 0 && bits & (1 << (v - 1)) != 0)
+                .collect();
+            satisfies(&assignments, &clauses)
+        });
+
+        match solve(n_variable, &clauses) {
+            Some(assignments) => {
+                assert!(brute_force);
+                assert!(satisfies(&assignments, &clauses));
+            }
+            None => assert!(!brute_force),
+        }
+    }
+}
+
+#[test]
+fn incremental_agrees_with_solving_from_scratch() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let n_variable = 10;
+
+    for _ in 0..20 {
+        let backend = CdclSolver::default();
+        let mut solver = backend.incremental(n_variable);
+        let mut clauses: Vec<Clause> = Vec::new();
+
+        // adds clauses until unsatisfiable, solving after each one
+        loop {
+            let clause: Clause = (0..3)
+                .map(|_| (rng.gen(), rng.gen_range(1, n_variable + 1)))
+                .collect();
+            solver.add_clause(&clause);
+            clauses.push(clause);
+
+            let decision = solver.solve(&Budget::default()).unwrap();
+            assert_eq!(decision.is_some(), solve(n_variable, &clauses).is_some());
+            match decision {
+                Some(assignments) => assert!(satisfies(&assignments, &clauses)),
+                None => break,
+            }
+        }
+    }
+}
+
+#[test]
+fn gives_up_past_the_budget() {
+    let (n_variable, clauses) = pigeonhole(8);
+
+    let past = Budget {
+        deadline: Some(Instant::now()),
+        ..Budget::default()
+    };
+    match CdclSolver::default().solve(n_variable, &clauses, &past) {
+        Err(SatError::Timeout) => {}
+        decision => panic!("unexpected decision {:?}", decision),
+    }
+
+    let cancelled = Budget {
+        cancelled: Some(Arc::new(AtomicBool::new(true))),
+        ..Budget::default()
+    };
+    match CdclSolver::default().solve(n_variable, &clauses, &cancelled) {
+        Err(SatError::Cancelled) => {}
+        decision => panic!("unexpected decision {:?}", decision),
+    }
+}
@@ -0,0 +1,39 @@
+// helpers shared by the integration tests, not all of them used by each
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use dbcop::db::history::{Event, Transaction};
+
+static N_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// an empty directory for `name`, apart from those of every other call, test
+// and test process
+pub fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("dbcop-{}", std::process::id()))
+        .join(format!("{}-{}", name, N_DIR.fetch_add(1, Ordering::SeqCst)));
+    if dir.is_dir() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// (write, variable, value) events of a committed transaction
+pub fn transaction(events: &[(bool, usize, usize)]) -> Transaction {
+    Transaction {
+        events: events
+            .iter()
+            .map(|&(write, variable, value)| Event {
+                write,
+                variable,
+                value,
+                success: true,
+            })
+            .collect(),
+        success: true,
+    }
+}
@@ -0,0 +1,50 @@
+extern crate dbcop;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use dbcop::db::history::History;
+use dbcop::verifier::differential;
+
+use common::dir;
+
+#[test]
+fn backends_agree_on_simulated_executions() {
+    let dir = dir("simulated");
+
+    let disagreements = differential::run(30, 3, 4, 4, 3, 42, &dir);
+
+    assert!(
+        disagreements.is_empty(),
+        "disagreements dumped in {:?}: {:?}",
+        dir,
+        disagreements
+    );
+}
+
+// minimized disagreements dumped by `dbcop differential` go to histories/regressions
+#[test]
+fn backends_agree_on_regressions() {
+    let regressions = Path::new(env!("CARGO_MANIFEST_DIR")).join("histories/regressions");
+    let dir = dir("regressions");
+
+    for entry in fs::read_dir(&regressions).expect("couldn't read regressions") {
+        let path = entry.unwrap().path();
+        let hist = History::load(&path).expect("couldn't load regression");
+
+        let disagreements =
+            differential::compare(hist.get_data(), None, &dir.join(path.file_stem().unwrap()));
+
+        assert!(
+            disagreements.is_empty(),
+            "{:?}: {:?}",
+            path,
+            disagreements
+                .iter()
+                .map(|d| (&d.model, &d.verdicts))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+extern crate dbcop;
+
+mod common;
+
+use dbcop::consistency::witness::{VerifyError, Witness};
+use dbcop::db::export::{to_cobra_logs, to_edn, to_text};
+use dbcop::db::history::Session;
+use dbcop::db::import::{from_cobra_logs, from_edn, from_text};
+use dbcop::verifier::Verifier;
+
+use common::{dir, transaction};
+
+// verdict of serializability after a round trip through cobra logs
+fn verify_cobra(name: &str, sessions: &[Session]) -> Result<(), VerifyError> {
+    let dir = dir(name);
+
+    to_cobra_logs(sessions, &dir.join("logs")).unwrap();
+    let imported = from_cobra_logs(&dir.join("logs")).unwrap();
+    assert_eq!(imported.len(), sessions.len());
+
+    let mut verifier = Verifier::new(dir);
+    verifier.model("ser");
+    verifier.verify(&imported).map(|_| ())
+}
+
+#[test]
+fn cobra_round_trip_keeps_verdicts() {
+    let serial = vec![
+        vec![
+            transaction(&[(false, 0, 0), (true, 0, 1)]),
+            transaction(&[(true, 1, 1)]),
+        ],
+        vec![transaction(&[(false, 0, 1), (false, 1, 1), (true, 0, 2)])],
+    ];
+    assert!(verify_cobra("serial", &serial).is_ok());
+
+    let write_skew = vec![
+        vec![transaction(&[(false, 0, 0), (false, 1, 0), (true, 0, 1)])],
+        vec![transaction(&[(false, 0, 0), (false, 1, 0), (true, 1, 1)])],
+    ];
+    assert!(verify_cobra("write-skew", &write_skew)
+        .unwrap_err()
+        .violation()
+        .is_some());
+
+    let thin_air = vec![vec![transaction(&[(false, 0, 7)])]];
+    match verify_cobra("thin-air", &thin_air) {
+        Err(VerifyError::Violation(violation)) => match violation.witness {
+            Witness::ThinAirRead { .. } => {}
+            witness => panic!("unexpected witness {:?}", witness),
+        },
+        decision => panic!("unexpected decision {:?}", decision),
+    }
+
+    let duplicate = vec![
+        vec![transaction(&[(true, 0, 1)])],
+        vec![transaction(&[(true, 0, 1)]), transaction(&[(true, 1, 2)])],
+        vec![transaction(&[(false, 1, 2)])],
+    ];
+    match verify_cobra("duplicate", &duplicate) {
+        Err(VerifyError::DuplicateWrite { .. }) => {}
+        decision => panic!("unexpected decision {:?}", decision),
+    }
+}
+
+// witness of read committed verification
+fn witness(sessions: &[Session]) -> Witness {
+    let mut verifier = Verifier::new(dir("aborted"));
+    verifier.model("rc");
+    match verifier.verify(sessions) {
+        Err(VerifyError::Violation(violation)) => violation.witness,
+        decision => panic!("unexpected decision {:?}", decision),
+    }
+}
+
+#[test]
+fn aborted_reads_survive_cobra_and_edn_but_not_text() {
+    let mut aborted = transaction(&[(true, 0, 1)]);
+    aborted.success = false;
+    let sessions = vec![vec![aborted], vec![transaction(&[(false, 0, 1)])]];
+
+    let dir = dir("aborted-logs");
+    to_cobra_logs(&sessions, &dir).unwrap();
+    match witness(&from_cobra_logs(&dir).unwrap()) {
+        Witness::AbortedRead { .. } => {}
+        witness => panic!("unexpected cobra witness {:?}", witness),
+    }
+
+    let mut edn = Vec::new();
+    to_edn(&sessions, &mut edn).unwrap();
+    match witness(&from_edn(&String::from_utf8(edn).unwrap()).unwrap()) {
+        Witness::AbortedRead { .. } => {}
+        witness => panic!("unexpected edn witness {:?}", witness),
+    }
+
+    // the text format has no aborted transactions
+    let mut text = Vec::new();
+    to_text(&sessions, &mut text).unwrap();
+    match witness(&from_text(&text[..]).unwrap()) {
+        Witness::ThinAirRead { .. } => {}
+        witness => panic!("unexpected text witness {:?}", witness),
+    }
+}
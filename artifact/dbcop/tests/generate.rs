@@ -0,0 +1,162 @@
+extern crate dbcop;
+extern crate rand;
+
+mod common;
+
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dbcop::db::history::{generate_history, generate_mult_histories, History};
+use dbcop::db::memory::Isolation;
+use dbcop::db::workload::{Application, KeyDistribution, Preset, Workload};
+use dbcop::verifier::differential::simulate;
+use dbcop::verifier::Verifier;
+
+use common::dir;
+
+#[test]
+fn histories_regenerate_from_their_params() {
+    let workload = Workload {
+        keys: KeyDistribution::Zipf(0.99),
+        read_only: 0.2,
+        max_event: 6,
+        read_modify_write: 0.5,
+        partition: true,
+        ..Workload::fixed(2)
+    };
+
+    let histories = generate_mult_histories(8, 4, 10, 10, &workload, 42);
+
+    for (hist, again) in histories
+        .iter()
+        .zip(generate_mult_histories(8, 4, 10, 10, &workload, 42).iter())
+    {
+        assert_eq!(hist.get_data(), again.get_data());
+        assert_eq!(hist.get_data(), &generate_history(hist.get_params()));
+    }
+
+    assert!(histories[0].get_data() != histories[1].get_data());
+}
+
+// written by `dbcop generate` before histories kept their workload and seed
+#[test]
+fn baseline_bincode_histories_still_load() {
+    let legacy = Path::new(env!("CARGO_MANIFEST_DIR")).join("histories/legacy/hist-00000.bincode");
+    let hist = History::load(&legacy).unwrap();
+
+    let params = hist.get_params();
+    assert_eq!(
+        (
+            params.get_n_node(),
+            params.get_n_variable(),
+            params.get_n_transaction()
+        ),
+        (2, 2, 2)
+    );
+    assert_eq!(params.get_workload(), &Workload::default());
+    assert_eq!(hist.get_data().len(), 2);
+
+    // and the current layout still loads as itself
+    let path = dir("current-layout").join("history.bincode");
+    let histories = generate_mult_histories(1, 2, 2, 2, &Workload::fixed(2), 7);
+    histories[0].save(&path).unwrap();
+    let again = History::load(&path).unwrap();
+    assert_eq!(again.get_data(), histories[0].get_data());
+    assert_eq!(
+        again.get_params().get_seed(),
+        histories[0].get_params().get_seed()
+    );
+}
+
+// every preset fails the level it stresses on a store just weaker than it
+#[test]
+fn presets_provoke_their_anomaly() {
+    let dir = dir("presets");
+
+    let mut rng = StdRng::seed_from_u64(42);
+
+    for &(preset, isolation, model) in [
+        (Preset::WriteSkew, Isolation::SnapshotIsolation, "ser"),
+        (
+            Preset::LongFork,
+            Isolation::ParallelSnapshotIsolation,
+            "pre",
+        ),
+        (Preset::LostUpdate, Isolation::Causal, "si"),
+        (Preset::CausalityChain, Isolation::UnorderedDelivery, "cc"),
+        (Preset::FracturedRead, Isolation::ReadCommitted, "ra"),
+    ]
+    .iter()
+    {
+        let workload = Workload {
+            preset: Some(preset),
+            ..Workload::default()
+        };
+
+        let n_failed = (0..50)
+            .filter(|_| {
+                let mut sessions = workload.generate(4, 6, 6, &mut rng);
+                simulate(&mut sessions, isolation, &mut rng);
+
+                let mut verifier = Verifier::new(dir.clone());
+                verifier.model(model);
+                match verifier.verify(&sessions) {
+                    Err(e) => e.violation().map(|v| v.level) == Some(preset.stresses()),
+                    Ok(_) => false,
+                }
+            })
+            .count();
+
+        assert!(n_failed > 0, "{:?} never failed on {:?}", preset, isolation);
+    }
+}
+
+#[test]
+fn presets_reject_too_few_sessions_or_variables() {
+    let long_fork = Workload {
+        preset: Some(Preset::LongFork),
+        ..Workload::default()
+    };
+    assert!(long_fork.check(3, 6).is_err());
+    assert!(long_fork.check(4, 6).is_ok());
+
+    let lost_update = Workload {
+        preset: Some(Preset::LostUpdate),
+        ..Workload::default()
+    };
+    assert!(lost_update.check(2, 0).is_err());
+    assert!(Workload::default().check(2, 0).is_err());
+
+    // fences take the last variables
+    let fenced = Workload {
+        fence: 2,
+        ..long_fork
+    };
+    assert!(fenced.check(4, 5).is_err());
+    assert!(fenced.check(4, 6).is_ok());
+}
+
+#[test]
+fn applications_pass_on_snapshot_isolation() {
+    let dir = dir("applications");
+
+    let mut rng = StdRng::seed_from_u64(42);
+
+    for name in ["tpcc", "twitter", "rubis"].iter() {
+        let workload = Workload {
+            application: Application::from_name(name, None),
+            ..Workload::default()
+        };
+
+        let mut sessions = workload.generate(4, 0, 20, &mut rng);
+        assert!(Verifier::gen_write_map(&sessions).is_ok());
+
+        simulate(&mut sessions, Isolation::SnapshotIsolation, &mut rng);
+
+        let mut verifier = Verifier::new(dir.clone());
+        verifier.model("si");
+        assert!(verifier.verify(&sessions).is_ok(), "{} fails si", name);
+    }
+}
@@ -0,0 +1,83 @@
+extern crate dbcop;
+
+mod common;
+
+use dbcop::consistency::witness::Witness;
+use dbcop::db::export::{to_edn, to_text};
+use dbcop::db::history::Session;
+use dbcop::db::import::{from_edn, from_text};
+use dbcop::verifier::Verifier;
+
+use common::{dir, transaction};
+
+fn sessions() -> Vec<Session> {
+    vec![
+        vec![
+            transaction(&[(false, 0, 0), (true, 0, 1)]),
+            transaction(&[(true, 1, 1)]),
+        ],
+        vec![transaction(&[(false, 0, 1), (false, 1, 0), (true, 1, 2)])],
+    ]
+}
+
+#[test]
+fn text_round_trip() {
+    let mut text = Vec::new();
+    to_text(&sessions(), &mut text).unwrap();
+    assert_eq!(from_text(&text[..]).unwrap(), sessions());
+}
+
+#[test]
+fn edn_round_trip() {
+    let mut edn = Vec::new();
+    to_edn(&sessions(), &mut edn).unwrap();
+    assert_eq!(
+        from_edn(&String::from_utf8(edn).unwrap()).unwrap(),
+        sessions()
+    );
+}
+
+const EDN: &str = r#"
+{:type :invoke, :f :txn, :value [[:w 1 1]], :process 0, :index 0}
+{:type :fail, :f :txn, :value [[:w 1 1]], :process 0, :index 1}
+{:type :invoke, :f :txn, :value [[:r 3 nil] [:w 2 2]], :process 1, :index 2}
+{:type :info, :f :txn, :value [[:r 3 nil] [:w 2 2]], :process 1, :index 3}
+{:type :invoke, :f :txn, :value [[:w 3 3]], :process 3, :index 4}
+{:type :info, :f :txn, :value [[:w 3 3]], :process 3, :index 5}
+{:type :invoke, :f :txn, :value [[:r 2 nil] [:r 1 nil]], :process 2, :index 6}
+{:type :ok, :f :txn, :value [[:r 2 2] [:r 1 1]], :process 2, :index 7}
+"#;
+
+#[test]
+fn edn_failed_and_indeterminate_transactions() {
+    let sessions = from_edn(EDN).unwrap();
+
+    assert_eq!(sessions.len(), 4);
+    // failed
+    assert!(!sessions[0][0].success);
+    // indeterminate, its write is read; its read is unknown
+    assert!(sessions[1][0].success);
+    assert_eq!(sessions[1][0].events.len(), 1);
+    // indeterminate and never read
+    assert!(!sessions[2][0].success);
+    assert!(sessions[3][0].success);
+
+    let mut verifier = Verifier::new(dir("failed-reads"));
+    verifier.model("rc");
+    match verifier.verify(&sessions) {
+        Err(e) => match e.violation().map(|v| &v.witness) {
+            Some(Witness::AbortedRead { read, write }) => {
+                assert_eq!(*read, (4, 0, 1));
+                assert_eq!(*write, (1, 0, 0));
+            }
+            witness => panic!("unexpected witness {:?}", witness),
+        },
+        Ok(_) => panic!("read of a failed write passes"),
+    }
+}
+
+#[test]
+fn edn_list_append_is_rejected() {
+    let edn = "{:type :ok, :f :txn, :value [[:append 1 1] [:r 1 [1]]], :process 0}";
+    assert!(from_edn(edn).is_err());
+}
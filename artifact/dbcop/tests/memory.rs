@@ -0,0 +1,87 @@
+extern crate dbcop;
+extern crate rand;
+
+mod common;
+
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dbcop::db::cluster::Cluster;
+use dbcop::db::history::{generate_mult_histories, History};
+use dbcop::db::memory::{Isolation, MemoryCluster};
+use dbcop::db::workload::Workload;
+use dbcop::verifier::differential::simulate;
+use dbcop::verifier::Verifier;
+
+use common::dir;
+
+// generates, executes on an in-memory cluster and verifies `model`; the
+// number of histories failing it
+fn n_failed(isolation: Isolation, model: &str) -> usize {
+    let dir = dir(&format!("{:?}-{}", isolation, model));
+
+    let mut cluster = MemoryCluster::new(isolation, 3);
+
+    generate_mult_histories(20, 3, 3, 6, &Workload::fixed(3), 42)
+        .iter()
+        .filter(|hist| {
+            let curr_dir = dir.join(format!("hist-{:05}", hist.get_id()));
+            fs::create_dir_all(&curr_dir).unwrap();
+            cluster.execute(hist, &curr_dir);
+
+            let executed = History::load(&curr_dir.join("history.bincode")).unwrap();
+            let mut verifier = Verifier::new(curr_dir);
+            verifier.model(model);
+            verifier.verify(executed.get_data()).is_err()
+        })
+        .count()
+}
+
+#[test]
+fn serializable_is_serializable() {
+    assert_eq!(n_failed(Isolation::Serializable, "ser"), 0);
+}
+
+#[test]
+fn snapshot_isolation_is_snapshot_isolation() {
+    assert_eq!(n_failed(Isolation::SnapshotIsolation, "si"), 0);
+}
+
+#[test]
+fn parallel_snapshot_isolation_is_causal() {
+    assert_eq!(n_failed(Isolation::ParallelSnapshotIsolation, "cc"), 0);
+}
+
+#[test]
+fn causal_is_causal() {
+    assert_eq!(n_failed(Isolation::Causal, "cc"), 0);
+}
+
+#[test]
+fn read_committed_is_read_committed() {
+    assert_eq!(n_failed(Isolation::ReadCommitted, "rc"), 0);
+}
+
+// whether threads interleave into a lost update is up to the scheduler, so
+// the executions are simulated from a seed instead
+#[test]
+fn lost_update_is_caught() {
+    let dir = dir("lost-update");
+
+    let n_failed = generate_mult_histories(20, 3, 3, 6, &Workload::fixed(3), 42)
+        .iter()
+        .filter(|hist| {
+            let mut sessions = hist.get_cloned_data();
+            let mut rng = StdRng::seed_from_u64(hist.get_params().get_seed());
+            simulate(&mut sessions, Isolation::LostUpdate, &mut rng);
+
+            let mut verifier = Verifier::new(dir.clone());
+            verifier.model("si");
+            verifier.verify(&sessions).is_err()
+        })
+        .count();
+
+    assert!(n_failed > 0);
+}
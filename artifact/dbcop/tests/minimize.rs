@@ -0,0 +1,71 @@
+extern crate dbcop;
+extern crate rand;
+
+mod common;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dbcop::consistency::Consistency;
+use dbcop::db::history::{generate_single_history, history_seed};
+use dbcop::db::memory::Isolation;
+use dbcop::verifier::differential::simulate;
+use dbcop::verifier::minimize::minimize;
+use dbcop::verifier::Verifier;
+
+use common::{dir, transaction};
+
+#[test]
+fn lost_update_shrinks_to_a_snapshot_isolation_violation() {
+    let dir = dir("lost-update");
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let configure = |verifier: &mut Verifier| verifier.model("si");
+
+    // lost updates show up in most executions of this size
+    let (histories, minimal, violation) = (0..20)
+        .filter_map(|i_hist| {
+            let mut histories = generate_single_history(5, 5, 20, 4, history_seed(7, i_hist));
+            simulate(&mut histories, Isolation::LostUpdate, &mut rng);
+            minimize(&histories, &dir, configure)
+                .unwrap()
+                .map(|(minimal, violation)| (histories, minimal, violation))
+        })
+        .next()
+        .expect("no lost update");
+
+    let n_transaction = |sessions: &[Vec<_>]| sessions.iter().map(|s| s.len()).sum::<usize>();
+    assert!(n_transaction(&minimal) < n_transaction(&histories));
+
+    let mut verifier = Verifier::new(dir);
+    configure(&mut verifier);
+    match verifier.verify(&minimal) {
+        Err(e) => assert_eq!(e.violation().map(|v| v.level), Some(violation.level)),
+        Ok(_) => panic!("minimized history passes"),
+    }
+}
+
+#[test]
+fn empty_transactions_keep_the_violation() {
+    let dir = dir("empty");
+
+    // a lost update on x, next to empty transactions and an empty session
+    let histories = vec![
+        vec![
+            transaction(&[]),
+            transaction(&[(false, 0, 0), (true, 0, 1)]),
+        ],
+        vec![
+            transaction(&[(false, 0, 0), (true, 0, 2)]),
+            transaction(&[]),
+        ],
+        vec![transaction(&[])],
+    ];
+
+    let (minimal, violation) = minimize(&histories, &dir, |verifier| verifier.model("si"))
+        .unwrap()
+        .expect("lost update passes si");
+
+    assert!(minimal.iter().flatten().all(|t| !t.events.is_empty()));
+    assert_eq!(violation.level, Consistency::SnapshotIsolation);
+}
@@ -0,0 +1,187 @@
+extern crate dbcop;
+extern crate rand;
+
+mod common;
+
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dbcop::consistency::witness::{VerifyError, Witness};
+use dbcop::db::cluster::Cluster;
+use dbcop::db::history::{generate_mult_histories, History, Session};
+use dbcop::db::memory::{Isolation, MemoryCluster};
+use dbcop::db::workload::Workload;
+use dbcop::verifier::differential::simulate;
+use dbcop::verifier::online::OnlineVerifier;
+use dbcop::verifier::Verifier;
+
+use common::{dir, transaction};
+
+// executes on an in-memory cluster with fences, verifying `model` online with
+// a window of 4 transactions; the number of histories failing it and of
+// collected transactions. every online violation must be one of the batch
+// verifier
+fn n_failed_online(isolation: Isolation, model: &str) -> (usize, usize) {
+    let dir = dir(&format!("{:?}-{}", isolation, model));
+
+    let mut cluster = MemoryCluster::new(isolation, 3);
+    let mut n_collected = 0;
+
+    let workload = Workload {
+        fence: 2,
+        ..Workload::fixed(3)
+    };
+
+    let n_failed = generate_mult_histories(20, 3, 6, 8, &workload, 42)
+        .iter()
+        .filter(|hist| {
+            let curr_dir = dir.join(format!("hist-{:05}", hist.get_id()));
+            fs::create_dir_all(&curr_dir).unwrap();
+
+            let mut online = OnlineVerifier::new(curr_dir.clone(), model, 3, 4);
+            let decision = cluster.execute_online(hist, &curr_dir, &mut online);
+            n_collected += online.n_collected();
+
+            let executed = History::load(&curr_dir.join("history.bincode")).unwrap();
+            let mut verifier = Verifier::new(curr_dir);
+            verifier.model(model);
+            if decision.is_err() {
+                assert!(verifier.verify(executed.get_data()).is_err());
+            }
+            decision.is_err()
+        })
+        .count();
+
+    (n_failed, n_collected)
+}
+
+// pushes the transactions of simulated sessions round-robin, ending every
+// session after its last one; the decision and the most transactions the
+// history held at once
+fn stream(online: &mut OnlineVerifier, sessions: &[Session]) -> (Result<(), VerifyError>, usize) {
+    let mut max_live = 0;
+    let n_round = sessions
+        .iter()
+        .map(|session| session.len())
+        .max()
+        .unwrap_or(0);
+    for i_transaction in 0..n_round {
+        for (i_node, session) in sessions.iter().enumerate() {
+            if let Some(transaction) = session.get(i_transaction) {
+                let mut decision = online.push((i_node + 1, i_transaction), transaction.clone());
+                if decision.is_ok() && i_transaction + 1 == session.len() {
+                    decision = online.end_session(i_node + 1);
+                }
+                max_live = std::cmp::max(max_live, online.n_live());
+                if decision.is_err() {
+                    return (decision, max_live);
+                }
+            }
+        }
+    }
+    (online.finish(), max_live)
+}
+
+#[test]
+fn snapshot_isolation_passes_online() {
+    let (n_failed, n_collected) = n_failed_online(Isolation::SnapshotIsolation, "si");
+    assert_eq!(n_failed, 0);
+    assert!(n_collected > 0);
+}
+
+#[test]
+fn causal_passes_online() {
+    assert_eq!(n_failed_online(Isolation::Causal, "cc").0, 0);
+}
+
+#[test]
+fn lost_update_is_caught_online() {
+    let dir = dir("lost-update-simulated");
+
+    let workload = Workload {
+        fence: 2,
+        ..Workload::fixed(3)
+    };
+
+    let n_failed = generate_mult_histories(20, 3, 6, 8, &workload, 42)
+        .iter()
+        .filter(|hist| {
+            let curr_dir = dir.join(format!("hist-{:05}", hist.get_id()));
+            fs::create_dir_all(&curr_dir).unwrap();
+
+            let mut sessions = hist.get_cloned_data();
+            let mut rng = StdRng::seed_from_u64(hist.get_params().get_seed());
+            simulate(&mut sessions, Isolation::LostUpdate, &mut rng);
+
+            let mut online = OnlineVerifier::new(curr_dir.clone(), "si", 3, 4);
+            let (decision, _) = stream(&mut online, &sessions);
+
+            let mut verifier = Verifier::new(curr_dir);
+            verifier.model("si");
+            if decision.is_err() {
+                assert!(verifier.verify(&sessions).is_err());
+            }
+            decision.is_err()
+        })
+        .count();
+
+    assert!(n_failed > 0);
+}
+
+#[test]
+fn fenced_history_stays_small_online() {
+    let workload = Workload {
+        fence: 2,
+        ..Workload::fixed(3)
+    };
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut sessions = workload.generate(3, 6, 300, &mut rng);
+    simulate(&mut sessions, Isolation::SnapshotIsolation, &mut rng);
+
+    let mut online = OnlineVerifier::new(dir("long-fenced"), "si", 3, 4);
+    let (decision, max_live) = stream(&mut online, &sessions);
+
+    assert!(decision.is_ok());
+    assert!(online.n_collected() > 1000);
+    assert!(max_live < 64, "{} transactions live at once", max_live);
+}
+
+#[test]
+fn violation_is_reported_before_the_end() {
+    let rmw = |value| transaction(&[(false, 0, 0), (true, 0, value)]);
+
+    let mut online = OnlineVerifier::new(dir("lost-update"), "si", 2, 1);
+    assert!(online.push((1, 0), rmw(1)).is_ok());
+    match online.push((2, 0), rmw(2)) {
+        Err(VerifyError::Violation(_)) => {}
+        _ => panic!("lost update not reported"),
+    }
+}
+
+#[test]
+fn overwritten_collected_read_is_reported() {
+    let mut online = OnlineVerifier::new(dir("overwritten-read"), "cc", 2, 1);
+    assert!(online.push((1, 0), transaction(&[(true, 0, 1)])).is_ok());
+    assert!(online.push((2, 0), transaction(&[(false, 0, 1)])).is_ok());
+    // both sessions saw x = 1 by now
+    assert!(online.n_collected() > 0);
+    assert!(online
+        .push((1, 1), transaction(&[(true, 0, 2), (true, 1, 1)]))
+        .is_ok());
+
+    // sees x = 2 through y, and reads x = 1
+    match online.push((2, 1), transaction(&[(false, 1, 1), (false, 0, 1)])) {
+        Err(VerifyError::Violation(violation)) => match violation.witness {
+            Witness::OverwrittenRead {
+                read,
+                write,
+                overwrite,
+            } => assert_eq!((read, write, overwrite), ((2, 1, 1), (1, 0, 0), (1, 1))),
+            witness => panic!("unexpected witness {:?}", witness),
+        },
+        decision => panic!("overwritten read not reported: {:?}", decision),
+    }
+}
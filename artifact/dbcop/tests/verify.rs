@@ -0,0 +1,136 @@
+extern crate dbcop;
+extern crate rand;
+
+mod common;
+
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dbcop::consistency::witness::{AnomalyKind, VerifyError, Witness};
+use dbcop::db::history::{generate_single_history, history_seed, Session};
+use dbcop::db::memory::Isolation;
+use dbcop::verifier::differential::simulate;
+use dbcop::verifier::Verifier;
+
+use common::{dir, transaction};
+
+// two sessions updating x from its initial value, and one writing y
+fn lost_update() -> Vec<Session> {
+    vec![
+        vec![transaction(&[(false, 0, 0), (true, 0, 1)])],
+        vec![transaction(&[(false, 0, 0), (true, 0, 2)])],
+        vec![transaction(&[(true, 1, 1)]), transaction(&[(true, 1, 2)])],
+    ]
+}
+
+#[test]
+fn sat_witness_leaves_out_unrelated_sessions() {
+    let mut verifier = Verifier::new(dir("sat-lost-update"));
+    verifier.model("si");
+    verifier.sat(true);
+
+    let witness = match verifier.verify(&lost_update()) {
+        Err(e) => e.violation().expect("not a violation").witness.clone(),
+        Ok(_) => panic!("lost update passes si"),
+    };
+
+    match witness {
+        Witness::NonLinearizable(transactions) => {
+            assert_eq!(transactions, vec![(1, 0), (2, 0)])
+        }
+        witness => panic!("unexpected witness {:?}", witness),
+    }
+}
+
+// reads x before and after another session writes it
+fn non_repeatable_read() -> Vec<Session> {
+    vec![
+        vec![transaction(&[(true, 0, 1)])],
+        vec![transaction(&[(false, 0, 0), (false, 0, 1)])],
+    ]
+}
+
+#[test]
+fn read_committed_allows_non_repeatable_reads() {
+    let mut verifier = Verifier::new(dir("non-repeatable-read"));
+
+    verifier.model("rc");
+    assert!(verifier.verify(&non_repeatable_read()).is_ok());
+
+    verifier.model("rr");
+    match verifier.verify(&non_repeatable_read()) {
+        Err(e) => match e.violation().map(|v| &v.witness) {
+            Some(Witness::NonRepeatableRead { .. }) => {}
+            witness => panic!("unexpected witness {:?}", witness),
+        },
+        Ok(_) => panic!("non repeatable read passes rr"),
+    }
+}
+
+#[test]
+fn timeout_stops_every_backend() {
+    let backends: [(&str, fn(&mut Verifier)); 3] = [
+        ("algo", |_| {}),
+        ("sat", |verifier| verifier.sat(true)),
+        ("polygraph", |verifier| verifier.polygraph(true)),
+    ];
+
+    for &(name, configure) in backends.iter() {
+        let mut verifier = Verifier::new(dir(&format!("timeout-{}", name)));
+        verifier.model("si");
+        verifier.timeout(Some(Duration::from_secs(0)));
+        configure(&mut verifier);
+
+        match verifier.verify(&lost_update()) {
+            Err(VerifyError::Unknown { .. }) => {}
+            decision => panic!("{} decided {:?}", name, decision),
+        }
+    }
+}
+
+#[test]
+fn anomaly_report_finds_the_lost_update_cycles() {
+    let mut verifier = Verifier::new(dir("report-lost-update"));
+    let report = verifier.anomaly_report(&lost_update()).unwrap();
+
+    // only the two updates of x form a cycle, session 3 is left out
+    assert_eq!(report.anomalies.len(), 1);
+    match report.anomalies[0] {
+        (AnomalyKind::G2, Witness::Cycle(ref cycle)) => {
+            assert!(cycle.iter().all(|&(u, _, v)| u.0 != 3 && v.0 != 3))
+        }
+        ref anomaly => panic!("unexpected anomaly {:?}", anomaly),
+    }
+}
+
+// polygraph violations are cycles, which the anomaly categories cover
+#[test]
+fn polygraph_violations_are_categorized() {
+    let mut n_failed = 0;
+    for i_hist in 0..40 {
+        let seed = history_seed(11, i_hist);
+        let mut sessions = generate_single_history(4, 3, 8, 3, seed);
+        simulate(
+            &mut sessions,
+            Isolation::Causal,
+            &mut StdRng::seed_from_u64(seed),
+        );
+
+        let mut verifier = Verifier::new(dir("polygraph-categories"));
+        verifier.model("ser");
+        verifier.polygraph(true);
+
+        if let Err(e) = verifier.verify(&sessions) {
+            let violation = e.violation().expect("not a violation");
+            assert!(
+                AnomalyKind::of(&violation.witness).is_some(),
+                "uncategorized {:?}",
+                violation.witness
+            );
+            n_failed += 1;
+        }
+    }
+    assert!(n_failed > 0);
+}